@@ -0,0 +1,308 @@
+use std::{collections::HashMap, time::Instant};
+
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+use crate::{
+    domain::{Battlesnake, Board, Coord, Direction, Game, GameState, Ruleset},
+    logic::{
+        move_validator::get_valid_moves,
+        rules::Rules,
+        simulate::{simulate, spawn_food, MAX_HEALTH},
+        strategy::{get_budgeted_strategy, Budget, Strategy},
+    },
+    session::Session,
+};
+
+const START_LENGTH: usize = 3;
+
+// Work our strategies do per move in arena games.
+pub const DEFAULT_DEPTH: u32 = 6;
+pub const DEFAULT_ITERATIONS: u32 = 1000;
+
+// Any move that doesn't lose right away, a baseline to compare against.
+pub struct RandomStrategy {
+    seed: u64,
+}
+
+impl RandomStrategy {
+    pub fn new(seed: u64) -> Self {
+        RandomStrategy { seed }
+    }
+}
+
+impl Strategy for RandomStrategy {
+    fn name(&self) -> &str {
+        "random"
+    }
+
+    fn choose_move(&self, state: &GameState, _session: &mut Session) -> Direction {
+        let rules = Rules::new(state.game.ruleset());
+        let valid_moves = get_valid_moves(&state.board, &state.you, &rules);
+
+        // Keep the order stable so seeded games are reproducible.
+        let moves: Vec<Direction> = rules
+            .topology
+            .neighbours(&state.board, &state.you.head)
+            .into_iter()
+            .filter(|dir| valid_moves.contains(dir))
+            .collect();
+
+        let mut rng = StdRng::seed_from_u64(self.seed.wrapping_add(state.turn as u64));
+
+        moves
+            .choose(&mut rng)
+            .copied()
+            .unwrap_or_else(|| rules.topology.neighbours(&state.board, &state.you.head)[0])
+    }
+}
+
+// Our strategies held to the budget, plus the random baseline.
+pub fn get_player(name: &str, budget: Budget) -> Option<Box<dyn Strategy>> {
+    match name {
+        "random" => Some(Box::new(RandomStrategy::new(budget.seed))),
+        _ => get_budgeted_strategy(name, budget),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Outcome {
+    // Index of the last snake standing, None for draws and solo games.
+    pub winner: Option<usize>,
+    pub turns: i32,
+}
+
+// How the games of an arena went for one player.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Tally {
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+}
+
+impl Tally {
+    pub fn add(&mut self, outcome: &Outcome, player: usize) {
+        match outcome.winner {
+            Some(winner) if winner == player => self.wins += 1,
+            Some(_) => self.losses += 1,
+            None => self.draws += 1,
+        }
+    }
+
+    pub fn merge(&mut self, other: &Tally) {
+        self.wins += other.wins;
+        self.losses += other.losses;
+        self.draws += other.draws;
+    }
+}
+
+// Plays whole games in process, under the rules of `simulate`.
+#[derive(Debug, Clone)]
+pub struct Arena {
+    pub ruleset: Ruleset,
+    pub width: u32,
+    pub height: u32,
+    // Sent to the players as the game timeout, in milliseconds.
+    pub timeout: u32,
+    pub max_turns: i32,
+}
+
+impl Default for Arena {
+    fn default() -> Self {
+        let mut ruleset = Ruleset::new("standard");
+        ruleset.settings.minimum_food = 1;
+        ruleset.settings.food_spawn_chance = 15;
+
+        Arena {
+            ruleset,
+            width: 11,
+            height: 11,
+            timeout: 500,
+            max_turns: 1000,
+        }
+    }
+}
+
+impl Arena {
+    // Every player gets a session of its own, as if it was a server.
+    pub fn play(&self, players: &[Box<dyn Strategy>], seed: u64) -> Outcome {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let game = Game::new(
+            &format!("arena-{}", seed),
+            self.ruleset.clone(),
+            self.timeout,
+        );
+        let rules = Rules::new(&self.ruleset);
+        let mut board = self.get_start(players.len(), &mut rng);
+        let mut sessions: Vec<Session> = players
+            .iter()
+            .map(|_| Session::new(Instant::now()))
+            .collect();
+        let mut turn = 0;
+
+        while turn < self.max_turns && !self.is_over(&board, players.len()) {
+            let moves: HashMap<String, Direction> = board
+                .snakes
+                .iter()
+                .map(|you| {
+                    let player = get_index(&you.id);
+                    let state = GameState {
+                        game: game.clone(),
+                        turn,
+                        board: board.clone(),
+                        you: you.clone(),
+                    };
+
+                    let session = &mut sessions[player];
+
+                    session.last_seen = Instant::now();
+                    session.observe(turn, &board, &rules);
+
+                    (you.id.clone(), players[player].choose_move(&state, session))
+                })
+                .collect();
+
+            board = simulate(&board, &moves, &rules);
+            spawn_food(&mut board, &self.ruleset.settings, &mut rng);
+            turn += 1;
+        }
+
+        let winner = match board.snakes.as_slice() {
+            [winner] if players.len() > 1 => Some(get_index(&winner.id)),
+            _ => None,
+        };
+
+        Outcome {
+            winner,
+            turns: turn,
+        }
+    }
+
+    fn is_over(&self, board: &Board, players: usize) -> bool {
+        match players {
+            1 => board.snakes.is_empty(),
+            _ => board.snakes.len() <= 1,
+        }
+    }
+
+    // Snakes start coiled up on random cells of the same colour, like on a
+    // chess board, so all of them can meet head to head.
+    fn get_start(&self, players: usize, rng: &mut StdRng) -> Board {
+        let cells: Vec<Coord> = (0..self.height as i32)
+            .flat_map(|y| (0..self.width as i32).map(move |x| Coord::new(x, y)))
+            .filter(|coord| (coord.x + coord.y) % 2 == 0)
+            .collect();
+
+        let snakes = cells
+            .choose_multiple(rng, players)
+            .enumerate()
+            .map(|(i, head)| Battlesnake {
+                id: format!("snake-{}", i),
+                name: format!("snake-{}", i),
+                health: MAX_HEALTH,
+                length: START_LENGTH as i32,
+                body: vec![*head; START_LENGTH],
+                head: *head,
+                latency: String::from("0"),
+                shout: None,
+            })
+            .collect();
+
+        let mut board = Board {
+            height: self.height,
+            width: self.width,
+            food: vec![],
+            snakes,
+            hazards: vec![],
+        };
+
+        spawn_food(&mut board, &self.ruleset.settings, rng);
+        board
+    }
+}
+
+fn get_index(id: &str) -> usize {
+    id.trim_start_matches("snake-").parse().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_arena() -> Arena {
+        Arena {
+            width: 7,
+            height: 7,
+            max_turns: 200,
+            ..Arena::default()
+        }
+    }
+
+    fn get_budget(seed: u64) -> Budget {
+        Budget {
+            depth: 2,
+            iterations: 50,
+            seed,
+        }
+    }
+
+    #[test]
+    fn same_seed_same_game() {
+        let arena = get_arena();
+        let play = || {
+            let players = vec![
+                get_player("random", get_budget(1)).unwrap(),
+                get_player("random", get_budget(2)).unwrap(),
+            ];
+
+            arena.play(&players, 42)
+        };
+
+        assert_eq!(play(), play());
+    }
+
+    #[test]
+    fn budgeted_searches_replay_the_same() {
+        let arena = Arena {
+            max_turns: 30,
+            ..get_arena()
+        };
+        let play = || {
+            let players = vec![
+                get_player("minimax", get_budget(1)).unwrap(),
+                get_player("mcts", get_budget(2)).unwrap(),
+            ];
+
+            arena.play(&players, 42)
+        };
+
+        assert_eq!(play(), play());
+    }
+
+    #[test]
+    fn solo_games_have_no_winner() {
+        let players = vec![get_player("random", get_budget(1)).unwrap()];
+
+        let outcome = get_arena().play(&players, 7);
+
+        assert_eq!(outcome.winner, None);
+        assert!(outcome.turns > 0);
+    }
+
+    #[test]
+    fn count_outcomes() {
+        let mut tally = Tally::default();
+
+        for winner in [Some(0), Some(1), None] {
+            tally.add(&Outcome { winner, turns: 1 }, 0);
+        }
+
+        assert_eq!(
+            tally,
+            Tally {
+                wins: 1,
+                losses: 1,
+                draws: 1
+            }
+        );
+    }
+}
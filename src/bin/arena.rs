@@ -0,0 +1,157 @@
+use std::{env, process::ExitCode, str::FromStr, thread};
+
+use battle_snake_rust::{
+    arena::{get_player, Arena, Tally, DEFAULT_DEPTH, DEFAULT_ITERATIONS},
+    domain::Ruleset,
+    logic::strategy::Budget,
+};
+
+const USAGE: &str = "Usage: arena [--games N] [--seed N] [--size N] [--mode NAME] \
+[--timeout MS] [--max-turns N] [--depth N] [--iterations N] PLAYER...
+
+Players: heuristic, minimax, mcts, random. Instead of the timeout, minimax
+searches up to --depth and mcts runs --iterations playouts every move, so the
+same seed plays the same games.";
+
+struct Options {
+    games: u64,
+    seed: u64,
+    arena: Arena,
+    depth: u32,
+    iterations: u32,
+    players: Vec<String>,
+}
+
+impl Options {
+    fn get_budget(&self, seed: u64) -> Budget {
+        Budget {
+            depth: self.depth,
+            iterations: self.iterations,
+            seed,
+        }
+    }
+}
+
+fn parse<T: FromStr>(name: &str, value: Option<String>) -> Result<T, String> {
+    value
+        .and_then(|value| value.parse().ok())
+        .ok_or(format!("{} needs a number", name))
+}
+
+fn get_options() -> Result<Options, String> {
+    let mut options = Options {
+        games: 100,
+        seed: 0,
+        arena: Arena::default(),
+        depth: DEFAULT_DEPTH,
+        iterations: DEFAULT_ITERATIONS,
+        players: vec![],
+    };
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--games" => options.games = parse(&arg, args.next())?,
+            "--seed" => options.seed = parse(&arg, args.next())?,
+            "--timeout" => options.arena.timeout = parse(&arg, args.next())?,
+            "--max-turns" => options.arena.max_turns = parse(&arg, args.next())?,
+            "--depth" => options.depth = parse(&arg, args.next())?,
+            "--iterations" => options.iterations = parse(&arg, args.next())?,
+            "--size" => {
+                let size = parse(&arg, args.next())?;
+                options.arena.width = size;
+                options.arena.height = size;
+            }
+            "--mode" => {
+                let settings = options.arena.ruleset.settings.clone();
+                let name = args.next().ok_or("--mode needs a name")?;
+
+                options.arena.ruleset = Ruleset {
+                    settings,
+                    ..Ruleset::new(&name)
+                };
+            }
+            player if get_player(player, options.get_budget(0)).is_some() => {
+                options.players.push(arg)
+            }
+            _ => return Err(format!("Unknown argument {}", arg)),
+        }
+    }
+
+    if options.players.is_empty() {
+        return Err(String::from("No players"));
+    }
+
+    Ok(options)
+}
+
+// Plays every game whose number is `offset` modulo `step`.
+fn run(options: &Options, offset: u64, step: u64) -> Vec<Tally> {
+    let mut tallies = vec![Tally::default(); options.players.len()];
+
+    for game in (offset..options.games).step_by(step as usize) {
+        let seed = options.seed + game;
+        let players: Vec<_> = options
+            .players
+            .iter()
+            .enumerate()
+            .filter_map(|(i, name)| get_player(name, options.get_budget(seed * 31 + i as u64)))
+            .collect();
+
+        let outcome = options.arena.play(&players, seed);
+
+        for (i, tally) in tallies.iter_mut().enumerate() {
+            tally.add(&outcome, i);
+        }
+    }
+
+    tallies
+}
+
+// Plays games between our strategies without a server, and prints how each
+// of them did.
+//
+//     cargo run --release --bin arena -- --games 1000 minimax heuristic
+fn main() -> ExitCode {
+    let options = match get_options() {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let threads = thread::available_parallelism().map_or(1, |n| n.get()) as u64;
+    let mut tallies = vec![Tally::default(); options.players.len()];
+
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|offset| {
+                let options = &options;
+                scope.spawn(move || run(options, offset, threads))
+            })
+            .collect();
+
+        for worker in workers {
+            for (total, tally) in tallies.iter_mut().zip(worker.join().unwrap_or_default()) {
+                total.merge(&tally);
+            }
+        }
+    });
+
+    println!("{} games, seed {}", options.games, options.seed);
+
+    for (i, (name, tally)) in options.players.iter().zip(tallies).enumerate() {
+        println!(
+            "snake-{} {:<8} {:>6} wins {:>6} losses {:>6} draws {:>6.1}% win rate",
+            i,
+            name,
+            tally.wins,
+            tally.losses,
+            tally.draws,
+            100.0 * tally.wins as f64 / options.games.max(1) as f64
+        );
+    }
+
+    ExitCode::SUCCESS
+}
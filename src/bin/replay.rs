@@ -0,0 +1,57 @@
+use std::{env, path::Path, process::ExitCode};
+
+use battle_snake_rust::{
+    arena::{DEFAULT_DEPTH, DEFAULT_ITERATIONS},
+    logic::strategy::{Budget, Strategies},
+    record,
+};
+
+// Replays recorded games through the current move logic and prints every
+// turn where it now decides differently. Every move goes to the strategy that
+// played it, held to the arena budget so replays are reproducible. Older
+// records that don't name it get the one the server picks from
+// `BATTLESNAKE_STRATEGY`.
+//
+//     cargo run --bin replay -- records/<game id>-<hash>.jsonl
+fn main() -> ExitCode {
+    let strategies = Strategies::from_env();
+    let budget = Budget {
+        depth: DEFAULT_DEPTH,
+        iterations: DEFAULT_ITERATIONS,
+        seed: 0,
+    };
+    let paths: Vec<String> = env::args().skip(1).collect();
+
+    if paths.is_empty() {
+        eprintln!("Usage: replay <record.jsonl>...");
+        return ExitCode::FAILURE;
+    }
+
+    for path in paths {
+        let records = match record::load(Path::new(&path)) {
+            Ok(records) => records,
+            Err(err) => {
+                eprintln!("{}: {}", path, err);
+                return ExitCode::FAILURE;
+            }
+        };
+
+        let game_id = records
+            .first()
+            .map(|record| record.request().game.id())
+            .unwrap_or_default();
+        let fallback = strategies.for_game(game_id).name();
+        let divergences = record::replay(&records, fallback, budget);
+
+        println!("{}: {} different moves", path, divergences.len());
+
+        for divergence in divergences {
+            println!(
+                "  turn {}: recorded {}, replayed {}",
+                divergence.turn, divergence.recorded, divergence.replayed
+            );
+        }
+    }
+
+    ExitCode::SUCCESS
+}
@@ -3,14 +3,125 @@ use serde::Serialize;
 use serde_json::Value;
 use std::collections::HashMap;
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Game {
     id: String,
-    ruleset: HashMap<String, Value>,
+    ruleset: Ruleset,
     timeout: u32,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+impl Game {
+    pub fn new(id: &str, ruleset: Ruleset, timeout: u32) -> Self {
+        Game {
+            id: String::from(id),
+            ruleset,
+            timeout,
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn ruleset(&self) -> &Ruleset {
+        &self.ruleset
+    }
+
+    pub fn timeout(&self) -> u32 {
+        self.timeout
+    }
+
+    pub fn mode(&self) -> GameMode {
+        self.ruleset.mode()
+    }
+}
+
+// See https://docs.battlesnake.com/api/objects/ruleset
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct Ruleset {
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub settings: RulesetSettings,
+    // Keys the engine sends that we don't model yet.
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+impl Ruleset {
+    pub fn new(name: &str) -> Self {
+        Ruleset {
+            name: String::from(name),
+            ..Ruleset::default()
+        }
+    }
+
+    pub fn mode(&self) -> GameMode {
+        GameMode::from_name(&self.name)
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RulesetSettings {
+    pub food_spawn_chance: i32,
+    pub minimum_food: i32,
+    // None when the engine leaves it out, which isn't the same as no damage.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hazard_damage_per_turn: Option<i32>,
+    pub hazard_map: String,
+    pub royale: RoyaleSettings,
+    pub squad: SquadSettings,
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RoyaleSettings {
+    pub shrink_every_n_turns: i32,
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SquadSettings {
+    pub allow_body_collisions: bool,
+    pub shared_elimination: bool,
+    pub shared_health: bool,
+    pub shared_length: bool,
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+// The game modes we know about, taken from the ruleset name.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum GameMode {
+    Standard,
+    Solo,
+    Royale,
+    Squad,
+    Constrictor,
+    Wrapped,
+    Unknown,
+}
+
+impl GameMode {
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "standard" => GameMode::Standard,
+            "solo" => GameMode::Solo,
+            "royale" => GameMode::Royale,
+            "squad" => GameMode::Squad,
+            "constrictor" => GameMode::Constrictor,
+            "wrapped" => GameMode::Wrapped,
+            _ => GameMode::Unknown,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Board {
     pub height: u32,
     pub width: u32,
@@ -43,7 +154,47 @@ impl Coord {
     }
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+// What the engine shows about our snake.
+// See https://docs.battlesnake.com/api/requests/info
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct InfoResponse {
+    pub apiversion: String,
+    pub author: String,
+    pub color: String,
+    pub head: String,
+    pub tail: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+impl Default for InfoResponse {
+    fn default() -> Self {
+        InfoResponse {
+            apiversion: String::from("1"),
+            author: String::from("leonardo fleitas alvarez"),
+            color: String::from("#222a34"),
+            head: String::from("default"),
+            tail: String::from("default"),
+            version: Some(String::from(env!("CARGO_PKG_VERSION"))),
+        }
+    }
+}
+
+impl InfoResponse {
+    // Colors must look like "#a1b2c3".
+    pub fn validate(&self) -> Result<(), String> {
+        let hex = self.color.strip_prefix('#').unwrap_or_default();
+
+        if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("Invalid hex color {:?}", self.color));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct GameState {
     pub game: Game,
     pub turn: i32,
@@ -51,6 +202,40 @@ pub struct GameState {
     pub you: Battlesnake,
 }
 
+// Our answer to a move request.
+// See https://docs.battlesnake.com/api/requests/move
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct MoveResponse {
+    #[serde(serialize_with = "serialize_move")]
+    pub r#move: Direction,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shout: Option<String>,
+}
+
+// Longer shouts are cut by the engine.
+pub const MAX_SHOUT_LENGTH: usize = 256;
+
+impl MoveResponse {
+    pub fn new(r#move: Direction) -> Self {
+        MoveResponse {
+            r#move,
+            shout: None,
+        }
+    }
+
+    // Cut to `MAX_SHOUT_LENGTH` characters, empty shouts are left out.
+    pub fn with_shout(mut self, shout: Option<String>) -> Self {
+        self.shout = shout
+            .map(|shout| shout.chars().take(MAX_SHOUT_LENGTH).collect::<String>())
+            .filter(|shout| !shout.is_empty());
+        self
+    }
+}
+
+fn serialize_move<S: serde::Serializer>(dir: &Direction, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(dir.as_str())
+}
+
 #[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone, Copy, Hash)]
 pub enum Direction {
     Up(Coord),
@@ -78,3 +263,92 @@ impl Direction {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_ruleset() {
+        let game: Game = serde_json::from_str(
+            r#"{
+                "id": "game-id",
+                "ruleset": {
+                    "name": "royale",
+                    "version": "v1.2.3",
+                    "settings": {
+                        "foodSpawnChance": 25,
+                        "minimumFood": 1,
+                        "hazardDamagePerTurn": 14,
+                        "hazardMap": "",
+                        "hazardMapAuthor": "",
+                        "royale": { "shrinkEveryNTurns": 5 },
+                        "squad": {
+                            "allowBodyCollisions": true,
+                            "sharedElimination": true,
+                            "sharedHealth": false,
+                            "sharedLength": false
+                        }
+                    }
+                },
+                "timeout": 500
+            }"#,
+        )
+        .unwrap();
+
+        let settings = &game.ruleset().settings;
+
+        assert_eq!(game.id(), "game-id");
+        assert_eq!(game.timeout(), 500);
+        assert_eq!(game.mode(), GameMode::Royale);
+        assert_eq!(settings.food_spawn_chance, 25);
+        assert_eq!(settings.hazard_damage_per_turn, Some(14));
+        assert_eq!(settings.royale.shrink_every_n_turns, 5);
+        assert!(settings.squad.allow_body_collisions);
+        assert!(!settings.squad.shared_health);
+        assert_eq!(
+            settings.extra.get("hazardMapAuthor"),
+            Some(&Value::String(String::new()))
+        );
+    }
+
+    #[test]
+    fn unknown_mode() {
+        assert_eq!(Ruleset::new("standard").mode(), GameMode::Standard);
+        assert_eq!(Ruleset::new("wrapped").mode(), GameMode::Wrapped);
+        assert_eq!(Ruleset::new("something-new").mode(), GameMode::Unknown);
+    }
+
+    #[test]
+    fn validate_colors() {
+        let info = |color: &str| InfoResponse {
+            color: String::from(color),
+            ..InfoResponse::default()
+        };
+
+        assert!(info("#00FFaa").validate().is_ok());
+        assert!(info("00ffaa").validate().is_err());
+        assert!(info("#0fa").validate().is_err());
+        assert!(info("#00ffzz").validate().is_err());
+    }
+
+    #[test]
+    fn serialize_move_response() {
+        let response = MoveResponse::new(Direction::Left(Coord::new(0, 1)));
+
+        assert_eq!(
+            serde_json::to_value(&response).unwrap(),
+            serde_json::json!({ "move": "left" })
+        );
+
+        let response = response.with_shout(Some("ñ".repeat(300)));
+        let value = serde_json::to_value(&response).unwrap();
+
+        assert_eq!(value["move"], "left");
+        assert_eq!(
+            value["shout"].as_str().map(|shout| shout.chars().count()),
+            Some(MAX_SHOUT_LENGTH)
+        );
+        assert_eq!(response.with_shout(Some(String::new())).shout, None);
+    }
+}
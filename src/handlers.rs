@@ -1,43 +1,110 @@
+use std::{panic, time::Instant};
+
+use rocket::fairing::AdHoc;
 use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome, Request};
 use rocket::serde::json::Json;
+use rocket::tokio::task;
 use rocket::{get, post};
-use serde_json::Value;
 
-use crate::domain::GameState;
+use crate::domain::{GameState, InfoResponse, MoveResponse};
 use crate::logic;
+use crate::snakes::Snake;
+
+// When the request reached the server, before it waited for a worker or its
+// body was read. Moves are due counting from there.
+#[derive(Debug, Clone, Copy)]
+pub struct Arrival(pub Instant);
+
+impl Arrival {
+    // Notes the arrival of every request, before any handler runs.
+    pub fn fairing() -> AdHoc {
+        AdHoc::on_request("Request arrival", |req, _| {
+            Box::pin(async move {
+                req.local_cache(|| Arrival(Instant::now()));
+            })
+        })
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Arrival {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(*req.local_cache(|| Arrival(Instant::now())))
+    }
+}
 
 #[get("/")]
-pub fn handle_index() -> Json<Value> {
-    Json(logic::info())
+pub fn handle_index(snake: &Snake) -> Json<InfoResponse> {
+    Json(logic::info(&snake.info))
 }
 
 #[post("/start", format = "json", data = "<start_req>")]
-pub fn handle_start(start_req: Json<GameState>) -> Status {
-    logic::start(
-        &start_req.game,
-        &start_req.turn,
-        &start_req.board,
-        &start_req.you,
-    );
+pub fn handle_start(start_req: Json<GameState>, snake: &Snake) -> Status {
+    let now = Instant::now();
+    let game_id = start_req.game.id();
+    let strategy = snake.strategies.for_game(game_id);
+
+    // Good time to forget the games that never ended.
+    snake.sessions.sweep(now);
+    snake.sessions.start(game_id, now);
+    snake.sessions.with(game_id, now, |session| {
+        logic::start(strategy, &start_req, session)
+    });
 
     Status::Ok
 }
 
 #[post("/move", format = "json", data = "<move_req>")]
-pub fn handle_move(move_req: Json<GameState>) -> Json<Value> {
-    let response = logic::get_move(
-        &move_req.game,
-        &move_req.turn,
-        &move_req.board,
-        &move_req.you,
-    );
+pub async fn handle_move(
+    move_req: Json<GameState>,
+    snake: &Snake,
+    arrival: Arrival,
+) -> Json<MoveResponse> {
+    let now = arrival.0;
+    let strategies = snake.strategies.clone();
+    let sessions = snake.sessions.clone();
+    let move_req = move_req.into_inner();
+
+    // The search keeps its thread busy until the deadline, so it stays off
+    // the workers serving the other requests.
+    let thinking = task::spawn_blocking(move || {
+        let strategy = strategies.for_game(move_req.game.id());
+        let response = sessions.with(move_req.game.id(), now, |session| {
+            let response = logic::get_move(strategy, &move_req, session);
+
+            session.timings.push(now.elapsed());
+            response
+        });
+
+        (move_req, response)
+    });
+    let (move_req, response) = thinking
+        .await
+        .unwrap_or_else(|err| panic::resume_unwind(err.into_panic()));
+
+    // Games that never ended are forgotten even if no new game starts.
+    snake.sessions.sweep_due(now);
+
+    let strategy = snake.strategies.for_game(move_req.game.id());
+
+    snake
+        .recorder
+        .record_move(&move_req, strategy.name(), &response, now.elapsed());
 
     Json(response)
 }
 
 #[post("/end", format = "json", data = "<end_req>")]
-pub fn handle_end(end_req: Json<GameState>) -> Status {
-    logic::end(&end_req.game, &end_req.turn, &end_req.board, &end_req.you);
+pub fn handle_end(end_req: Json<GameState>, snake: &Snake) -> Status {
+    let strategy = snake.strategies.for_game(end_req.game.id());
+
+    snake.recorder.record_end(&end_req);
+    snake.sessions.end(end_req.game.id(), |session| {
+        logic::end(strategy, &end_req, session)
+    });
 
     Status::Ok
 }
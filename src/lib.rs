@@ -1,3 +1,7 @@
+pub mod arena;
 pub mod domain;
 pub mod handlers;
 pub mod logic;
+pub mod record;
+pub mod session;
+pub mod snakes;
@@ -1,73 +1,83 @@
+pub mod bitboard;
+mod deadline;
+mod fallback;
+#[cfg(test)]
+mod fixtures;
 mod food_finder;
+mod head_to_head;
+pub(crate) mod hunger;
+mod mcts;
 mod move_refinator;
-mod move_validator;
+pub mod move_validator;
+pub mod rules;
+mod search;
+pub mod simulate;
+mod space;
+pub mod strategy;
+mod tail_chaser;
+mod territory;
+pub mod topology;
+pub(crate) mod transposition;
+mod zobrist;
 
 use log::info;
-use serde_json::{json, Value};
 
 use crate::{
-    domain::{Battlesnake, Board, Game},
-    logic::food_finder::get_next_step,
+    domain::{GameState, InfoResponse, MoveResponse},
+    logic::{rules::Rules, strategy::Strategy},
+    session::Session,
 };
 
 // info is called when you create your Battlesnake on play.battlesnake.com
 // and controls your Battlesnake's appearance
 // TIP: If you open your Battlesnake URL in a browser you should see this data
-pub fn info() -> Value {
+pub fn info(info: &InfoResponse) -> InfoResponse {
     info!("INFO");
 
-    return json!({
-        "apiversion": "1",
-        "author": "leonardo fleitas alvarez",
-        "color": "#222a34", // TODO: Choose color
-        "head": "default", // TODO: Choose head
-        "tail": "default", // TODO: Choose tail
-    });
+    info.clone()
 }
 
 // start is called when your Battlesnake begins a game
-pub fn start(_game: &Game, _turn: &i32, _board: &Board, _you: &Battlesnake) {
-    info!("GAME START");
+pub fn start(strategy: &dyn Strategy, state: &GameState, session: &mut Session) {
+    let game = &state.game;
+
+    session.observe(state.turn, &state.board, &Rules::new(game.ruleset()));
+
+    info!(
+        "GAME START: {} ({:?}, {})",
+        game.id(),
+        game.mode(),
+        strategy.name()
+    );
+    strategy.start(state, session);
 }
 
 // end is called when your Battlesnake finishes a game
-pub fn end(_game: &Game, _turn: &i32, _board: &Board, _you: &Battlesnake) {
-    info!("GAME OVER");
+pub fn end(strategy: &dyn Strategy, state: &GameState, session: &mut Session) {
+    strategy.end(state, session);
+    info!(
+        "GAME OVER: {} turns, {:?} per move",
+        state.turn,
+        session.average_timing().unwrap_or_default()
+    );
 }
 
 // move is called on every turn and returns your next move
 // Valid moves are "up", "down", "left", or "right"
 // See https://docs.battlesnake.com/api/example-move for available data
-pub fn get_move(_game: &Game, turn: &i32, board: &Board, you: &Battlesnake) -> Value {
-    let valid_moves = move_validator::get_valid_moves(board, you);
-
-    if valid_moves.is_empty() {
-        return json!({ "move": "up" });
-    }
-
-    // Are there any safe moves left?
-    let safe_moves = valid_moves.into_iter().collect::<Vec<_>>();
-
-    let recommended = move_refinator::recommend_move(&safe_moves, you, board);
-
-    if let Some(direction) = recommended {
-        info!("MOVE {}: {}", turn, direction.as_str());
-        return json!({ "move": direction.as_str() });
-    }
-
-    let refined_moves = move_refinator::refined_movements(&safe_moves, &board, &you);
+pub fn get_move(strategy: &dyn Strategy, state: &GameState, session: &mut Session) -> MoveResponse {
+    let rules = Rules::new(state.game.ruleset());
 
-    let options = if refined_moves.len() > 0 {
-        refined_moves
-    } else {
-        safe_moves
-    };
+    session.observe(state.turn, &state.board, &rules);
+    session.plan = None;
 
-    // TODO: Step 4 - Move towards food instead of random, to regain health and survive longer
-    // let food = &board.food;
-    let next_move = get_next_step(board, you, &options);
-    let chosen = next_move.as_str();
+    let chosen = strategy.choose_move(state, session);
 
-    info!("MOVE {}: {}", turn, chosen);
-    return json!({ "move": chosen });
+    info!(
+        "MOVE {}: {} ({})",
+        state.turn,
+        chosen.as_str(),
+        strategy.name()
+    );
+    MoveResponse::new(chosen).with_shout(strategy.shout(state, session))
 }
@@ -0,0 +1,601 @@
+use std::{collections::VecDeque, sync::Arc};
+
+use crate::domain::{Board, Coord, Direction};
+
+use super::{rules::Rules, simulate::MAX_HEALTH, topology::Dimensions};
+
+// Enough words for a 32x32 board, bigger than anything the engine offers.
+const WORDS: usize = 16;
+pub const MAX_CELLS: usize = WORDS * 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct BitSet([u64; WORDS]);
+
+impl BitSet {
+    pub fn get(&self, i: usize) -> bool {
+        (self.0[i / 64] >> (i % 64)) & 1 == 1
+    }
+
+    pub fn set(&mut self, i: usize) {
+        self.0[i / 64] |= 1 << (i % 64);
+    }
+
+    pub fn clear(&mut self, i: usize) {
+        self.0[i / 64] &= !(1 << (i % 64));
+    }
+
+    #[cfg(test)]
+    pub fn count(&self) -> u32 {
+        self.0.iter().map(|word| word.count_ones()).sum()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..MAX_CELLS).filter(|i| self.get(*i))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snake {
+    pub health: i32,
+    // Cell indexes, head first. Stacked segments repeat the index.
+    pub body: VecDeque<u16>,
+    pub alive: bool,
+}
+
+impl Snake {
+    pub fn head(&self) -> usize {
+        self.body[0] as usize
+    }
+
+    pub fn len(&self) -> usize {
+        self.body.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.body.is_empty()
+    }
+
+    fn grow(&mut self) {
+        if let Some(tail) = self.body.back().copied() {
+            self.body.push_back(tail);
+        }
+
+        self.health = MAX_HEALTH;
+    }
+}
+
+// Compact board meant to be built once per request and copied around while
+// searching. Snakes keep their index for the whole game, dead ones are
+// flagged instead of removed.
+#[derive(Debug, Clone)]
+pub struct BitBoard {
+    pub width: u32,
+    pub height: u32,
+    pub occupied: BitSet,
+    pub food: BitSet,
+    pub hazards: BitSet,
+    pub snakes: Vec<Snake>,
+    // These never change during a search, so copies share them.
+    hazard_stacks: Arc<Vec<u8>>,
+    ids: Arc<Vec<String>>,
+}
+
+impl Dimensions for BitBoard {
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+}
+
+impl BitBoard {
+    // None if the board is too big to fit.
+    pub fn new(board: &Board) -> Option<Self> {
+        if (board.width * board.height) as usize > MAX_CELLS {
+            return None;
+        }
+
+        let mut bitboard = BitBoard {
+            width: board.width,
+            height: board.height,
+            occupied: BitSet::default(),
+            food: BitSet::default(),
+            hazards: BitSet::default(),
+            snakes: vec![],
+            hazard_stacks: Arc::new(vec![]),
+            ids: Arc::new(board.snakes.iter().map(|snake| snake.id.clone()).collect()),
+        };
+
+        let inside = |coord: &&Coord| {
+            coord.x >= 0
+                && (coord.x as u32) < board.width
+                && coord.y >= 0
+                && (coord.y as u32) < board.height
+        };
+
+        for food in board.food.iter().filter(inside) {
+            bitboard.food.set(bitboard.index(food));
+        }
+
+        let mut stacks = vec![0; (board.width * board.height) as usize];
+
+        for hazard in board.hazards.iter().filter(inside) {
+            let cell = bitboard.index(hazard);
+
+            bitboard.hazards.set(cell);
+            stacks[cell] += 1;
+        }
+
+        bitboard.hazard_stacks = Arc::new(stacks);
+        bitboard.snakes = board
+            .snakes
+            .iter()
+            .map(|snake| Snake {
+                health: snake.health,
+                body: snake
+                    .body
+                    .iter()
+                    .filter(inside)
+                    .map(|coord| bitboard.index(coord) as u16)
+                    .collect(),
+                alive: true,
+            })
+            .collect();
+        bitboard.update_occupied();
+
+        Some(bitboard)
+    }
+
+    // Back to the API representation, without the dead snakes. Only ids are
+    // kept, so they are used as names too.
+    pub fn to_board(&self) -> Board {
+        let snakes = self
+            .snakes
+            .iter()
+            .enumerate()
+            .filter(|(_, snake)| snake.alive)
+            .map(|(i, snake)| {
+                let body: Vec<Coord> = snake
+                    .body
+                    .iter()
+                    .map(|cell| self.coord(*cell as usize))
+                    .collect();
+
+                crate::domain::Battlesnake {
+                    id: self.ids[i].clone(),
+                    name: self.ids[i].clone(),
+                    health: snake.health,
+                    head: body[0],
+                    length: body.len() as i32,
+                    body,
+                    latency: String::from("0"),
+                    shout: None,
+                }
+            })
+            .collect();
+
+        let hazards = self
+            .hazard_stacks
+            .iter()
+            .enumerate()
+            .flat_map(|(cell, stack)| vec![self.coord(cell); *stack as usize])
+            .collect();
+
+        Board {
+            height: self.height,
+            width: self.width,
+            food: self.food.iter().map(|cell| self.coord(cell)).collect(),
+            snakes,
+            hazards,
+        }
+    }
+
+    pub fn index(&self, coord: &Coord) -> usize {
+        (coord.y as u32 * self.width + coord.x as u32) as usize
+    }
+
+    pub fn coord(&self, cell: usize) -> Coord {
+        let cell = cell as u32;
+
+        Coord::new((cell % self.width) as i32, (cell / self.width) as i32)
+    }
+
+    pub fn snake_index(&self, id: &str) -> Option<usize> {
+        self.ids.iter().position(|x| x == id)
+    }
+
+    pub fn is_alive(&self, snake: usize) -> bool {
+        self.snakes.get(snake).is_some_and(|snake| snake.alive)
+    }
+
+    pub fn alive(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.snakes.len()).filter(|i| self.snakes[*i].alive)
+    }
+
+    pub fn hazard_cost(&self, cell: usize, rules: &Rules) -> i32 {
+        self.hazard_stack(cell) as i32 * rules.hazard_damage
+    }
+
+    // Hazards stacked on the cell, each one dealing its damage.
+    pub fn hazard_stack(&self, cell: usize) -> u8 {
+        self.hazard_stacks[cell]
+    }
+
+    // Whether a snake moving into the cell this turn would hit a body.
+    pub fn is_blocked(&self, cell: usize, rules: &Rules) -> bool {
+        self.occupied.get(cell) && !self.is_vacating_tail(cell, rules)
+    }
+
+    // Same rules as `move_validator::get_valid_moves`.
+    pub fn valid_moves(&self, snake: usize, rules: &Rules) -> Vec<Direction> {
+        let snake = &self.snakes[snake];
+
+        if !snake.alive {
+            return vec![];
+        }
+
+        rules
+            .topology
+            .neighbours(self, &self.coord(snake.head()))
+            .into_iter()
+            .filter(|dir| {
+                let coord = dir.get_coord();
+
+                if !rules.topology.contains(self, coord) {
+                    return false;
+                }
+
+                let cell = self.index(coord);
+                let cost = self.hazard_cost(cell, rules);
+
+                !self.is_blocked(cell, rules)
+                    && (cost == 0 || self.food.get(cell) || snake.health - 1 - cost > 0)
+            })
+            .collect()
+    }
+
+    // Advance one turn, same rules as `simulate::simulate`. `moves` has the
+    // move of every snake by index, `None` keeps going in the same direction.
+    pub fn advance(&mut self, moves: &[Option<Direction>], rules: &Rules) {
+        for i in 0..self.snakes.len() {
+            if !self.snakes[i].alive {
+                continue;
+            }
+
+            let next = match moves.get(i).copied().flatten() {
+                Some(dir) => rules.topology.normalize(self, *dir.get_coord()),
+                None => self.default_move(i, rules),
+            };
+            let cell = Some(next)
+                .filter(|next| rules.topology.contains(self, next))
+                .map(|next| self.index(&next));
+            let snake = &mut self.snakes[i];
+
+            snake.body.pop_back();
+            snake.health -= 1;
+
+            // Out of bounds snakes are eliminated before anything else.
+            match cell {
+                Some(cell) => snake.body.push_front(cell as u16),
+                None => snake.alive = false,
+            }
+        }
+
+        // Snakes that eat on a hazard don't take its damage.
+        for i in 0..self.snakes.len() {
+            if !self.snakes[i].alive {
+                continue;
+            }
+
+            let head = self.snakes[i].head();
+
+            if !self.food.get(head) {
+                self.snakes[i].health -= self.hazard_cost(head, rules);
+            }
+        }
+
+        let mut eaten = BitSet::default();
+
+        for snake in self.snakes.iter_mut().filter(|snake| snake.alive) {
+            // Constrictor snakes grow every turn, eating or not.
+            if self.food.get(snake.head()) {
+                eaten.set(snake.head());
+                snake.grow();
+            } else if !rules.tails_vacate() {
+                snake.grow();
+            }
+        }
+
+        for cell in eaten.iter() {
+            self.food.clear(cell);
+        }
+
+        self.eliminate();
+        self.update_occupied();
+    }
+
+    fn eliminate(&mut self) {
+        for snake in self.snakes.iter_mut() {
+            if snake.health <= 0 {
+                snake.alive = false;
+            }
+        }
+
+        let mut bodies = BitSet::default();
+
+        for snake in self.snakes.iter().filter(|snake| snake.alive) {
+            for cell in snake.body.iter().skip(1) {
+                bodies.set(*cell as usize);
+            }
+        }
+
+        let eliminated: Vec<usize> = self
+            .alive()
+            .filter(|i| {
+                let snake = &self.snakes[*i];
+
+                bodies.get(snake.head())
+                    || self.alive().any(|j| {
+                        let other = &self.snakes[j];
+
+                        j != *i && other.head() == snake.head() && other.len() >= snake.len()
+                    })
+            })
+            .collect();
+
+        for i in eliminated {
+            self.snakes[i].alive = false;
+        }
+    }
+
+    fn update_occupied(&mut self) {
+        self.occupied = BitSet::default();
+
+        for snake in self.snakes.iter().filter(|snake| snake.alive) {
+            for cell in snake.body.iter() {
+                self.occupied.set(*cell as usize);
+            }
+        }
+    }
+
+    fn is_vacating_tail(&self, cell: usize, rules: &Rules) -> bool {
+        rules.tails_vacate()
+            && self.snakes.iter().filter(|snake| snake.alive).any(|snake| {
+                let length = snake.len();
+
+                length > 1
+                    && snake.body[length - 1] as usize == cell
+                    && snake.body[length - 2] as usize != cell
+            })
+    }
+
+    // Repeat the move that brought the neck to the head, or go up.
+    fn default_move(&self, snake: usize, rules: &Rules) -> Coord {
+        let body = &self.snakes[snake].body;
+        let head = self.coord(body[0] as usize);
+
+        let index = body
+            .get(1)
+            .and_then(|neck| {
+                rules
+                    .topology
+                    .neighbours(self, &self.coord(*neck as usize))
+                    .iter()
+                    .position(|dir| *dir.get_coord() == head)
+            })
+            .unwrap_or(0);
+
+        *rules.topology.neighbours(self, &head)[index].get_coord()
+    }
+
+    // Voronoi partition of the free cells, like `territory::get_territories`
+    // but treating every body segment as a wall. Returns the cells and food
+    // owned by each snake, by index.
+    pub fn territories(&self, rules: &Rules) -> Vec<(usize, usize)> {
+        const CONTESTED: usize = usize::MAX;
+
+        let mut claimed = self.occupied;
+        let mut claims: Vec<Option<(usize, usize)>> = vec![None; MAX_CELLS];
+        let mut territories = vec![(0, 0); self.snakes.len()];
+        let mut frontier: Vec<(usize, usize)> =
+            self.alive().map(|i| (self.snakes[i].head(), i)).collect();
+
+        while !frontier.is_empty() {
+            let mut reached = vec![];
+
+            for (cell, owner) in frontier {
+                let length = self.snakes[owner].len();
+
+                for dir in rules.topology.neighbours(self, &self.coord(cell)) {
+                    if !rules.topology.contains(self, dir.get_coord()) {
+                        continue;
+                    }
+
+                    let next = self.index(dir.get_coord());
+
+                    if claimed.get(next) {
+                        continue;
+                    }
+
+                    match claims[next] {
+                        None => {
+                            claims[next] = Some((owner, length));
+                            reached.push(next);
+                        }
+                        Some((_, best)) if length > best => claims[next] = Some((owner, length)),
+                        Some((other, best)) if length == best && other != owner => {
+                            claims[next] = Some((CONTESTED, best))
+                        }
+                        _ => {}
+                    }
+                }
+            }
+
+            frontier = vec![];
+
+            for cell in reached {
+                claimed.set(cell);
+
+                if let Some((owner, _)) = claims[cell].filter(|(owner, _)| *owner != CONTESTED) {
+                    territories[owner].0 += 1;
+
+                    if self.food.get(cell) {
+                        territories[owner].1 += 1;
+                    }
+
+                    frontier.push((cell, owner));
+                }
+            }
+        }
+
+        territories
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use super::*;
+    use crate::{
+        domain::Ruleset,
+        logic::{
+            fixtures::{get_board, get_snake_with_health},
+            move_validator::get_valid_moves,
+            simulate::simulate,
+        },
+    };
+
+    fn get_position() -> Board {
+        let board = get_board(
+            7,
+            vec![
+                get_snake_with_health(
+                    "you",
+                    &[
+                        Coord::new(3, 3),
+                        Coord::new(2, 3),
+                        Coord::new(2, 2),
+                        Coord::new(2, 2),
+                    ],
+                    50,
+                ),
+                get_snake_with_health(
+                    "enemy",
+                    &[Coord::new(4, 4), Coord::new(5, 4), Coord::new(6, 4)],
+                    10,
+                ),
+            ],
+            &[Coord::new(3, 4), Coord::new(0, 0)],
+        );
+
+        Board {
+            hazards: vec![Coord::new(3, 2), Coord::new(3, 2), Coord::new(4, 3)],
+            ..board
+        }
+    }
+
+    #[test]
+    fn convert_back_and_forth() {
+        let board = get_position();
+        let bitboard = BitBoard::new(&board).unwrap();
+
+        assert_eq!(bitboard.occupied.count(), 6);
+        assert_eq!(bitboard.food.count(), 2);
+        assert_eq!(bitboard.to_board().snakes[0].body, board.snakes[0].body);
+        assert_eq!(bitboard.to_board().hazards.len(), 3);
+        assert_eq!(bitboard.to_board().food.len(), 2);
+    }
+
+    #[test]
+    fn same_moves_as_validator() {
+        let board = get_position();
+        let bitboard = BitBoard::new(&board).unwrap();
+
+        for rules in [
+            Rules::default(),
+            Rules::new(&Ruleset::new("constrictor")),
+            Rules::new(&Ruleset::new("wrapped")),
+        ] {
+            for (i, snake) in board.snakes.iter().enumerate() {
+                let expected = get_valid_moves(&board, snake, &rules);
+                let moves: HashSet<Direction> =
+                    bitboard.valid_moves(i, &rules).into_iter().collect();
+
+                assert_eq!(moves, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn same_turn_as_simulate() {
+        let board = get_position();
+
+        let standard = Rules::default();
+        let constrictor = Rules::new(&Ruleset::new("constrictor"));
+
+        for (rules, you, enemy) in [
+            (
+                &standard,
+                Direction::Up(Coord::new(3, 4)),
+                Direction::Left(Coord::new(3, 4)),
+            ),
+            (
+                &standard,
+                Direction::Down(Coord::new(3, 2)),
+                Direction::Down(Coord::new(4, 3)),
+            ),
+            (
+                &standard,
+                Direction::Right(Coord::new(4, 3)),
+                Direction::Up(Coord::new(4, 5)),
+            ),
+            (
+                &constrictor,
+                Direction::Up(Coord::new(3, 4)),
+                Direction::Down(Coord::new(4, 3)),
+            ),
+        ] {
+            let moves = HashMap::from([(String::from("you"), you), (String::from("enemy"), enemy)]);
+            let expected = simulate(&board, &moves, rules);
+
+            let mut bitboard = BitBoard::new(&board).unwrap();
+            bitboard.advance(&[Some(you), Some(enemy)], rules);
+            let next = bitboard.to_board();
+
+            let mut food = next.food.clone();
+            food.sort_by_key(|coord| (coord.x, coord.y));
+            let mut expected_food = expected.food.clone();
+            expected_food.sort_by_key(|coord| (coord.x, coord.y));
+
+            assert_eq!(food, expected_food);
+            assert_eq!(next.snakes.len(), expected.snakes.len());
+
+            for (snake, expected) in next.snakes.iter().zip(expected.snakes.iter()) {
+                assert_eq!(snake.id, expected.id);
+                assert_eq!(snake.body, expected.body);
+                assert_eq!(snake.health, expected.health);
+            }
+        }
+    }
+
+    #[test]
+    fn split_territories() {
+        let board = get_board(
+            5,
+            vec![
+                get_snake_with_health("left", &[Coord::new(0, 2), Coord::new(0, 1)], 50),
+                get_snake_with_health("right", &[Coord::new(4, 2), Coord::new(4, 1)], 50),
+            ],
+            &[Coord::new(1, 4)],
+        );
+
+        let territories = BitBoard::new(&board)
+            .unwrap()
+            .territories(&Rules::default());
+
+        assert_eq!(territories, vec![(8, 1), (8, 0)]);
+    }
+}
@@ -0,0 +1,104 @@
+use std::{
+    env,
+    time::{Duration, Instant},
+};
+
+use crate::domain::{Battlesnake, Game};
+
+// Least time kept aside for the network and the engine, in milliseconds. It
+// can be changed with the `BATTLESNAKE_LATENCY_MARGIN` environment variable.
+const DEFAULT_LATENCY_MARGIN: u64 = 150;
+
+// Kept on top of the measured latency, as it changes from turn to turn.
+const LATENCY_SLACK: Duration = Duration::from_millis(30);
+
+pub fn latency_margin() -> Duration {
+    let margin = env::var("BATTLESNAKE_LATENCY_MARGIN")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_LATENCY_MARGIN);
+
+    Duration::from_millis(margin)
+}
+
+// The engine tells us how long our last answer took to reach it, and
+// `timings` how long we spent on it. The rest went to the network and the
+// engine, and will again. The margin never drops below `latency_margin`, but
+// grows with a slow network.
+pub fn get_margin(you: &Battlesnake, timings: &[Duration]) -> Duration {
+    let latency = you.latency.parse::<u64>().ok().filter(|ms| *ms > 0);
+    let margin = latency_margin();
+
+    match (latency, timings.last()) {
+        (Some(latency), Some(thinking)) => {
+            margin.max(Duration::from_millis(latency).saturating_sub(*thinking) + LATENCY_SLACK)
+        }
+        _ => margin,
+    }
+}
+
+// When we must have a move ready, counting from `start`, the moment the
+// request arrived.
+pub fn get_deadline(game: &Game, start: Instant, margin: Duration) -> Instant {
+    let timeout = Duration::from_millis(game.timeout() as u64);
+
+    start + timeout.saturating_sub(margin)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Coord, Ruleset};
+
+    #[test]
+    fn keep_margin() {
+        let game = Game::new("game", Ruleset::new("standard"), 500);
+        let start = Instant::now();
+
+        let deadline = get_deadline(&game, start, Duration::from_millis(150));
+
+        assert_eq!(deadline - start, Duration::from_millis(350));
+    }
+
+    #[test]
+    fn measure_margin() {
+        let mut you = Battlesnake {
+            id: String::from("you"),
+            name: String::from("you"),
+            health: 100,
+            length: 1,
+            body: vec![Coord::new(0, 0)],
+            head: Coord::new(0, 0),
+            latency: String::from("0"),
+            shout: None,
+        };
+        let timings = [Duration::from_millis(300)];
+
+        assert_eq!(get_margin(&you, &timings), latency_margin());
+
+        you.latency = String::from("380");
+        assert_eq!(get_margin(&you, &[]), latency_margin());
+        assert_eq!(
+            get_margin(&you, &timings),
+            latency_margin().max(Duration::from_millis(80) + LATENCY_SLACK)
+        );
+
+        // A slow network takes more than the configured margin.
+        you.latency = String::from("800");
+        assert_eq!(
+            get_margin(&you, &timings),
+            latency_margin().max(Duration::from_millis(500) + LATENCY_SLACK)
+        );
+        assert!(get_margin(&you, &timings) > Duration::from_millis(500));
+    }
+
+    #[test]
+    fn margin_bigger_than_timeout() {
+        let game = Game::new("game", Ruleset::new("standard"), 100);
+        let start = Instant::now();
+
+        let deadline = get_deadline(&game, start, Duration::from_millis(150));
+
+        assert_eq!(deadline, start);
+    }
+}
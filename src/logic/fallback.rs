@@ -0,0 +1,235 @@
+use crate::domain::{Battlesnake, Board, Coord, Direction};
+
+use super::rules::Rules;
+
+// Lethal hazards might still be survived if the damage of the ruleset isn't
+// what we think, which is more than walls and bodies can offer.
+const HAZARD_CHANCE: f64 = 0.05;
+
+// Last resort when every move looks deadly. Moves are ranked by how likely
+// we are to survive them, since moves are resolved at the same time for
+// everybody and some of them may turn out fine. Between equally bad moves,
+// the one more likely to take an even snake down with us wins.
+pub fn get_last_resort(board: &Board, you: &Battlesnake, rules: &Rules) -> Direction {
+    let moves = rules.topology.neighbours(board, &you.head);
+
+    moves
+        .into_iter()
+        .map(|dir| {
+            let next = dir.get_coord();
+            let rank = (
+                get_survival_chance(board, you, rules, next),
+                get_trade_chance(board, you, rules, next),
+            );
+
+            (dir, rank)
+        })
+        .fold(
+            None,
+            |best: Option<(Direction, (f64, f64))>, (dir, rank)| match best {
+                Some((_, best_rank)) if best_rank >= rank => best,
+                _ => Some((dir, rank)),
+            },
+        )
+        .map_or(moves[0], |(dir, _)| dir)
+}
+
+// Rough chance of still being alive after moving our head to the coord. Even
+// head to heads kill us as surely as longer ones.
+fn get_survival_chance(board: &Board, you: &Battlesnake, rules: &Rules, next: &Coord) -> f64 {
+    if !rules.topology.contains(board, next) || rules.solid_body(&you.body).contains(next) {
+        return 0.0;
+    }
+
+    let mut chance = 1.0;
+
+    if !board.food.contains(next) && you.health - 1 - rules.hazard_cost(board, next) <= 0 {
+        chance *= HAZARD_CHANCE;
+    }
+
+    for snake in board.snakes.iter().filter(|snake| snake.id != you.id) {
+        if rules.solid_body(&snake.body).contains(next) {
+            // Bodies of snakes removed before collisions are checked are
+            // gone by then.
+            chance *= get_death_chance(board, snake, rules);
+        } else if snake.body.last() == Some(next) {
+            chance *= 1.0 - get_eating_chance(board, snake, rules);
+        }
+
+        let arriving = get_arrival_chance(board, snake, rules, next);
+
+        if snake.body.len() >= you.body.len() {
+            chance *= 1.0 - arriving;
+        }
+    }
+
+    chance
+}
+
+// Chance of an even snake meeting us head on at the coord, and dying along
+// with us.
+fn get_trade_chance(board: &Board, you: &Battlesnake, rules: &Rules, next: &Coord) -> f64 {
+    let surviving: f64 = board
+        .snakes
+        .iter()
+        .filter(|snake| snake.id != you.id && snake.body.len() == you.body.len())
+        .map(|snake| 1.0 - get_arrival_chance(board, snake, rules, next))
+        .product();
+
+    1.0 - surviving
+}
+
+// Chance of the snake starving or hitting a wall this turn, the eliminations
+// resolved before anything else.
+fn get_death_chance(board: &Board, snake: &Battlesnake, rules: &Rules) -> f64 {
+    let moves = rules.topology.neighbours(board, &snake.head);
+    let inside = moves
+        .iter()
+        .filter(|dir| rules.topology.contains(board, dir.get_coord()))
+        .count();
+
+    if inside == 0 {
+        return 1.0;
+    }
+
+    if snake.health <= 1 {
+        return 1.0 - get_eating_chance(board, snake, rules);
+    }
+
+    0.0
+}
+
+// Snakes that eat keep their tail where it is.
+fn get_eating_chance(board: &Board, snake: &Battlesnake, rules: &Rules) -> f64 {
+    let moves = rules.topology.neighbours(board, &snake.head);
+    let food = moves
+        .iter()
+        .filter(|dir| board.food.contains(dir.get_coord()))
+        .count();
+
+    food as f64 / moves.len() as f64
+}
+
+// Chance of the snake moving its head to the coord, if all its moves were
+// equally likely.
+fn get_arrival_chance(board: &Board, snake: &Battlesnake, rules: &Rules, next: &Coord) -> f64 {
+    let moves: Vec<Direction> = rules
+        .topology
+        .neighbours(board, &snake.head)
+        .into_iter()
+        .filter(|dir| rules.topology.contains(board, dir.get_coord()))
+        .filter(|dir| !rules.solid_body(&snake.body).contains(dir.get_coord()))
+        .collect();
+
+    match moves.iter().any(|dir| dir.get_coord() == next) {
+        true => 1.0 / moves.len() as f64,
+        false => 0.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logic::fixtures::{get_board, get_snake_with_health};
+
+    fn get_you(health: i32) -> Battlesnake {
+        get_snake_with_health(
+            "you",
+            &[
+                Coord::new(0, 4),
+                Coord::new(0, 3),
+                Coord::new(0, 2),
+                Coord::new(0, 1),
+            ],
+            health,
+        )
+    }
+
+    #[test]
+    fn go_through_starving_snake() {
+        // Walls up and left, our body down and the body of an enemy that
+        // starves this turn on the right.
+        let you = get_you(50);
+        let enemy = get_snake_with_health(
+            "enemy",
+            &[
+                Coord::new(2, 4),
+                Coord::new(1, 4),
+                Coord::new(1, 3),
+                Coord::new(1, 2),
+            ],
+            1,
+        );
+        let board = get_board(5, vec![you.clone(), enemy], &[]);
+
+        assert_eq!(
+            get_last_resort(&board, &you, &Rules::default()),
+            Direction::Right(Coord::new(1, 4))
+        );
+    }
+
+    #[test]
+    fn prefer_hazard_to_wall() {
+        let you = get_you(10);
+        let board = Board {
+            hazards: vec![Coord::new(1, 4)],
+            ..get_board(5, vec![you.clone()], &[])
+        };
+        let rules = Rules {
+            hazard_damage: 100,
+            ..Rules::default()
+        };
+
+        assert_eq!(
+            get_last_resort(&board, &you, &rules),
+            Direction::Right(Coord::new(1, 4))
+        );
+    }
+
+    #[test]
+    fn prefer_even_head_to_head() {
+        // An even snake may meet us on the left, a longer one on the right.
+        let you = get_snake_with_health(
+            "you",
+            &[Coord::new(2, 2), Coord::new(2, 1), Coord::new(2, 0)],
+            50,
+        );
+        let even = get_snake_with_health(
+            "even",
+            &[Coord::new(1, 3), Coord::new(1, 4), Coord::new(0, 4)],
+            50,
+        );
+        let longer = get_snake_with_health(
+            "longer",
+            &[
+                Coord::new(3, 3),
+                Coord::new(3, 4),
+                Coord::new(4, 4),
+                Coord::new(4, 3),
+            ],
+            50,
+        );
+        let board = get_board(5, vec![you.clone(), even, longer], &[]);
+        let rules = Rules::default();
+
+        let left = get_survival_chance(&board, &you, &rules, &Coord::new(1, 2));
+        let right = get_survival_chance(&board, &you, &rules, &Coord::new(3, 2));
+
+        // Just as deadly, but the even snake may go down with us.
+        assert_eq!(left, right);
+        assert!(right > 0.0);
+        assert!(get_trade_chance(&board, &you, &rules, &Coord::new(1, 2)) > 0.0);
+        assert_eq!(
+            get_trade_chance(&board, &you, &rules, &Coord::new(3, 2)),
+            0.0
+        );
+        assert_eq!(
+            get_last_resort(&board, &you, &rules),
+            Direction::Left(Coord::new(1, 2))
+        );
+        assert_eq!(
+            get_survival_chance(&board, &you, &rules, &Coord::new(2, 1)),
+            0.0
+        );
+    }
+}
@@ -0,0 +1,31 @@
+use crate::domain::{Battlesnake, Board, Coord};
+
+// Snakes and boards the tests of the move logic are built from.
+
+pub fn get_snake(id: &str, body: &[Coord]) -> Battlesnake {
+    get_snake_with_health(id, body, 100)
+}
+
+pub fn get_snake_with_health(id: &str, body: &[Coord], health: i32) -> Battlesnake {
+    Battlesnake {
+        id: String::from(id),
+        name: String::from(id),
+        health,
+        length: body.len() as i32,
+        body: body.to_vec(),
+        head: body[0],
+        latency: String::from("0"),
+        shout: None,
+    }
+}
+
+// Square board without hazards.
+pub fn get_board(size: u32, snakes: Vec<Battlesnake>, food: &[Coord]) -> Board {
+    Board {
+        height: size,
+        width: size,
+        food: food.to_vec(),
+        snakes,
+        hazards: vec![],
+    }
+}
@@ -1,138 +1,375 @@
-use std::{collections::VecDeque, usize};
+// The original move logic predates clippy, its idioms are kept as they are.
+#![allow(
+    clippy::into_iter_on_ref,
+    clippy::legacy_numeric_constants,
+    clippy::ptr_arg
+)]
+
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, VecDeque},
+    usize,
+};
 
 use crate::domain::{Battlesnake, Board, Coord, Direction};
 
-use super::move_validator::get_valid_moves;
+use super::{
+    bitboard::BitBoard, hunger::Hunger, move_validator::get_bitboard_moves, rules::Rules,
+    simulate::MAX_HEALTH, space::get_free_turns, topology::Topology,
+};
 
 #[derive(Debug, Clone)]
-struct State {
-    body: Vec<Coord>,
-    head: Coord,
+pub(super) struct State {
+    pub(super) body: Vec<Coord>,
+    pub(super) head: Coord,
+    pub(super) health: i32,
 }
 
 impl State {
-    fn new(body: Vec<Coord>) -> Self {
+    fn new(body: Vec<Coord>, health: i32) -> Self {
         State {
             body: body.clone(),
             head: body[0],
+            health,
         }
     }
 }
 
 #[derive(Debug, Clone)]
-struct Step {
-    dir: Direction,
-    state: State,
+pub(super) struct Step {
+    pub(super) dir: Direction,
+    pub(super) state: State,
+    // Health burned to get here.
+    cost: i32,
+    turns: usize,
+    // Cost plus the least health still needed to reach the target.
+    estimate: i32,
 }
 
 impl Step {
-    fn new(dir: Direction, state: State) -> Self {
-        Step { dir, state }
+    fn new(dir: Direction, state: State, cost: i32, turns: usize) -> Self {
+        Step {
+            dir,
+            state,
+            cost,
+            turns,
+            estimate: cost,
+        }
     }
 
-    // Move the battlesnake
-    fn walk(&self, next_step: Direction) -> Self {
+    // Our snake after taking its first move.
+    pub(super) fn first(dir: Direction, you: &Battlesnake, board: &Board, rules: &Rules) -> Self {
+        Step::new(dir, State::new(you.body.clone(), you.health), 0, 0).walk(dir, board, rules)
+    }
+
+    // Move the battlesnake, eating the food on the way. Food stays on the
+    // board, so walking back over it feeds us again.
+    pub(super) fn walk(&self, next_step: Direction, board: &Board, rules: &Rules) -> Self {
         let mut new_body: Vec<Coord> = vec![*next_step.get_coord()];
         new_body.extend_from_slice(&self.state.body);
-        new_body.pop();
 
-        Step::new(next_step, State::new(new_body))
+        let damage = 1 + rules.hazard_cost(board, next_step.get_coord());
+        let eats = board.food.contains(next_step.get_coord());
+
+        if rules.tails_vacate() {
+            new_body.pop();
+
+            // The tail stays where it is for a turn.
+            if let Some(tail) = new_body.last().copied().filter(|_| eats) {
+                new_body.push(tail);
+            }
+        }
+
+        let health = match eats {
+            true => MAX_HEALTH,
+            false => self.state.health - damage,
+        };
+
+        Step::new(
+            next_step,
+            State::new(new_body, health),
+            self.cost + damage,
+            self.turns + 1,
+        )
+    }
+
+    fn towards(mut self, board: &Board, rules: &Rules, target: &Coord) -> Self {
+        self.estimate = self.cost + get_distance(board, rules, self.dir.get_coord(), target);
+        self
+    }
+}
+
+// Reversed so the binary heap pops the most promising step first.
+impl Ord for Step {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.cmp(&self.estimate)
+    }
+}
+
+impl PartialOrd for Step {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
     }
 }
 
-pub fn get_next_step(board: &Board, you: &Battlesnake, options: &Vec<Direction>) -> Direction {
+impl PartialEq for Step {
+    fn eq(&self, other: &Self) -> bool {
+        self.estimate == other.estimate
+    }
+}
+
+impl Eq for Step {}
+
+// Cheapest way we found to some food.
+#[derive(Debug, Clone, Copy)]
+struct Path {
+    dir: Direction,
+    cost: i32,
+    turns: usize,
+}
+
+// Turns ahead of the closest rival we'd be worth this much health, when
+// healthy.
+const SAFETY_WEIGHT: f64 = 2.0;
+
+// Being further ahead than this doesn't make the food any safer.
+const MAX_MARGIN: usize = 5;
+
+// Goes for the food that best trades off how far it is, how far ahead of the
+// other snakes we'd get there and how hungry we are. Food another snake
+// reaches first is left alone. Food a snake as long as us reaches at the same
+// time is only worth it when we need to feed, and when growing we only go
+// where we get first. None when no food is worth going for, or we'd rather
+// not eat.
+pub fn get_next_step(
+    board: &Board,
+    you: &Battlesnake,
+    rules: &Rules,
+    options: &[Direction],
+    hunger: Hunger,
+) -> Option<Direction> {
+    if hunger == Hunger::Idle {
+        return None;
+    }
+
+    let rivals: Vec<(usize, Vec<Vec<Option<usize>>>)> = board
+        .snakes
+        .iter()
+        .filter(|snake| snake.id != you.id)
+        .map(|snake| (snake.body.len(), get_turns(board, rules, &snake.head)))
+        .collect();
+
+    let paths: Vec<(Coord, Path)> = board
+        .food
+        .iter()
+        .filter_map(|food| Some((*food, find_path(board, you, rules, options, food)?)))
+        .collect();
+
+    let score = |food: &Coord, path: &Path| -> Option<f64> {
+        if path.cost >= you.health {
+            return None;
+        }
+
+        let mut margin = MAX_MARGIN;
+
+        for (length, turns) in rivals.iter() {
+            let Some(turns) = turns[food.y as usize][food.x as usize] else {
+                continue;
+            };
+
+            // Lost, whatever we do.
+            if turns < path.turns {
+                return None;
+            }
+
+            // A head on collision with a longer rival kills us, an even
+            // one kills both and is only worth it when we need to feed.
+            if turns == path.turns
+                && (*length > you.body.len()
+                    || (*length == you.body.len() && hunger != Hunger::Feed))
+            {
+                return None;
+            }
+
+            margin = margin.min(turns - path.turns);
+        }
+
+        // Growing, we only eat where we get before anybody else.
+        if hunger == Hunger::Grow && margin == 0 {
+            return None;
+        }
+
+        let safety = SAFETY_WEIGHT * margin as f64 * you.health as f64 / 100.0;
+
+        Some(path.cost as f64 - safety)
+    };
+
+    let best = paths
+        .iter()
+        .filter_map(|(food, path)| Some((score(food, path)?, path)))
+        .min_by(|(a, _), (b, _)| a.total_cmp(b))
+        .map(|(_, path)| path);
+
+    best.map(|path| path.dir)
+}
+
+// A* from our head to the target, weighted by the health each step burns.
+fn find_path(
+    board: &Board,
+    you: &Battlesnake,
+    rules: &Rules,
+    options: &[Direction],
+    target: &Coord,
+) -> Option<Path> {
+    // Built once, every step is checked against the same board.
+    let bitboard = BitBoard::new(board)?;
+
     // Get the initial values for the queue
-    let mut queue: VecDeque<Step> = options
+    let mut queue: BinaryHeap<Step> = options
         .iter()
-        .map(|d| Step::new(*d, State::new(you.body.clone())).walk(*d))
+        .map(|d| Step::first(*d, you, board, rules).towards(board, rules, target))
         .collect();
 
-    // Initializing path matrix, that it's gonna be used as
-    // visited, and to track the path.
+    // Initializing path matrix, that it's gonna be used to track
+    // the path, and the cost matrix with the cheapest known cost
+    // for each cell.
     let mut path: Vec<Vec<Option<Coord>>> =
         vec![vec![None; board.width as usize]; board.height as usize];
+    let mut costs: Vec<Vec<Option<i32>>> =
+        vec![vec![None; board.width as usize]; board.height as usize];
     path[you.head.y as usize][you.head.x as usize] = Some(Coord::new(-1, -1));
+    costs[you.head.y as usize][you.head.x as usize] = Some(0);
 
-    // Mark as visited the initial valid movements.
+    // Mark the initial valid movements.
     for step in queue.iter() {
         let Coord { x: new_x, y: new_y } = step.dir.get_coord();
         let (new_x, new_y) = (*new_x as usize, *new_y as usize);
 
-        if path[new_y][new_x].is_none() {
+        if costs[new_y][new_x].is_none_or(|cost| step.cost < cost) {
             path[new_y][new_x] = Some(Coord::new(you.head.x, you.head.y));
+            costs[new_y][new_x] = Some(step.cost);
         }
     }
 
-    // To store the coord of the found food.
-    let mut last_coord = *options[0].get_coord();
-
-    // Bfs
-    while let Some(step) = queue.pop_front() {
+    while let Some(step) = queue.pop() {
         let Coord { x, y } = step.dir.get_coord();
         let (x, y) = (*x as usize, *y as usize);
 
+        // Skip steps that were already reached in a cheaper way.
+        if costs[y][x].is_some_and(|cost| cost < step.cost) {
+            continue;
+        }
+
         let new_step = Coord::new(x as i32, y as i32);
 
-        if is_food(&new_step, &board.food) {
-            last_coord = new_step;
-            break;
+        if new_step == *target {
+            // Get the coord of the initial option tracking the path
+            // of the found food in the path matrix.
+            let dir_coord = track_path(&path, &new_step)?;
+
+            // Search for which initial option has the same coord as the
+            // tracked one.
+            let dir = *options
+                .into_iter()
+                .find(|dir| *dir.get_coord() == dir_coord)?;
+
+            return Some(Path {
+                dir,
+                cost: step.cost,
+                turns: step.turns,
+            });
         }
 
         let new_snake = Battlesnake {
             head: step.state.head,
             body: step.state.body.clone(),
+            health: step.state.health,
             ..you.clone()
         };
 
         // Get the new possible steps.
-        let new_steps: Vec<_> = get_valid_moves(board, &new_snake)
+        let new_steps: Vec<_> = get_bitboard_moves(&bitboard, &new_snake, rules)
             .into_iter()
-            .map(|dir| step.walk(dir))
+            .map(|dir| step.walk(dir, board, rules).towards(board, rules, target))
             .collect();
 
         // Interate the new possible steps and add then if the
-        // node it's not visited.
+        // node is reached in a cheaper way.
         for step in new_steps {
             let Coord { x: new_x, y: new_y } = step.dir.get_coord();
             let (new_x, new_y) = (*new_x as usize, *new_y as usize);
 
-            if path[new_y][new_x].is_none() {
+            if costs[new_y][new_x].is_none_or(|cost| step.cost < cost) {
                 path[new_y][new_x] = Some(Coord::new(x as i32, y as i32));
-                queue.push_back(step);
+                costs[new_y][new_x] = Some(step.cost);
+                queue.push(step);
             }
         }
     }
 
-    // Get the coord of the initial option tracking the path
-    // of the found food in the path matrix.
-    let dir_coord = track_path(&path, &last_coord);
+    None
+}
+
+// Fewest moves between two cells on an empty board, never more than it
+// actually takes.
+fn get_distance(board: &Board, rules: &Rules, from: &Coord, to: &Coord) -> i32 {
+    let (dx, dy) = ((from.x - to.x).abs(), (from.y - to.y).abs());
 
-    // Search for which initial option has the same coord as the
-    // tracked one.
-    *options
-        .into_iter()
-        .find(|dir| *dir.get_coord() == dir_coord)
-        .unwrap()
+    match rules.topology {
+        Topology::Bounded => dx + dy,
+        Topology::Wrapped => dx.min(board.width as i32 - dx) + dy.min(board.height as i32 - dy),
+    }
 }
 
-fn is_food(position: &Coord, food: &[Coord]) -> bool {
-    food.contains(position)
+// Turns the snake with its head on `from` needs to reach every cell, going
+// around bodies that won't have moved away by then.
+pub(super) fn get_turns(board: &Board, rules: &Rules, from: &Coord) -> Vec<Vec<Option<usize>>> {
+    let free_at = get_free_turns(board, rules);
+    let mut turns = vec![vec![None; board.width as usize]; board.height as usize];
+
+    if !rules.topology.contains(board, from) {
+        return turns;
+    }
+
+    let mut queue: VecDeque<(Coord, usize)> = VecDeque::from([(*from, 0)]);
+    turns[from.y as usize][from.x as usize] = Some(0);
+
+    while let Some((coord, turn)) = queue.pop_front() {
+        for dir in rules.topology.neighbours(board, &coord) {
+            let next = *dir.get_coord();
+
+            if !rules.topology.contains(board, &next) {
+                continue;
+            }
+
+            let (x, y) = (next.x as usize, next.y as usize);
+
+            if turns[y][x].is_some() || free_at[y][x] > turn + 1 {
+                continue;
+            }
+
+            turns[y][x] = Some(turn + 1);
+            queue.push_back((next, turn + 1));
+        }
+    }
+
+    turns
 }
 
-fn track_path(path: &Vec<Vec<Option<Coord>>>, last_coord: &Coord) -> Coord {
+fn track_path(path: &Vec<Vec<Option<Coord>>>, last_coord: &Coord) -> Option<Coord> {
     let mut prev_coord = *last_coord;
-    let mut current_coord = path[last_coord.y as usize][last_coord.x as usize].unwrap();
+    let mut current_coord = path[last_coord.y as usize][last_coord.x as usize]?;
 
     loop {
         let Coord { x, y } = current_coord;
         let (x, y) = (x as usize, y as usize);
 
         if path[y][x] == Some(Coord::new(-1, -1)) {
-            return prev_coord;
+            return Some(prev_coord);
         }
 
         prev_coord = current_coord;
-        current_coord = path[y][x].unwrap();
+        current_coord = path[y][x]?;
     }
 }
 
@@ -185,11 +422,213 @@ mod tests {
             shout: None,
         };
 
-        let valid_moves = move_validator::get_valid_moves(&board, &battlesnake);
+        let rules = Rules::default();
+        let valid_moves = move_validator::get_valid_moves(&board, &battlesnake, &rules);
+        let safe_moves = valid_moves.into_iter().collect::<Vec<_>>();
+
+        let path = find_path(&board, &battlesnake, &rules, &safe_moves, &Coord::new(5, 2));
+
+        assert_eq!(
+            path.map(|path| path.dir),
+            Some(Direction::Right(Coord::new(6, 4)))
+        );
+
+        // The enemy gets there first though.
+        let next_step = get_next_step(&board, &battlesnake, &rules, &safe_moves, Hunger::Feed);
+
+        assert_eq!(next_step, None)
+    }
+
+    #[test]
+    fn avoid_hazard_path() {
+        let board = Board {
+            height: 10,
+            width: 10,
+            food: vec![Coord::new(5, 1)],
+            snakes: vec![],
+            hazards: vec![Coord::new(5, 3), Coord::new(5, 2), Coord::new(4, 4)],
+        };
+
+        let battlesnake = Battlesnake {
+            id: String::from("test"),
+            name: String::from("test"),
+            health: 100,
+            length: 3,
+            body: vec![Coord::new(5, 4), Coord::new(5, 5), Coord::new(5, 6)],
+            head: Coord::new(5, 4),
+            latency: String::from("test"),
+            shout: None,
+        };
+
+        let rules = Rules::default();
+        let valid_moves = move_validator::get_valid_moves(&board, &battlesnake, &rules);
         let safe_moves = valid_moves.into_iter().collect::<Vec<_>>();
 
-        let next_step = get_next_step(&board, &battlesnake, &safe_moves);
+        let next_step = get_next_step(&board, &battlesnake, &rules, &safe_moves, Hunger::Feed);
+
+        assert_eq!(next_step, Some(Direction::Right(Coord::new(6, 4))))
+    }
+
+    fn get_rival_board(rival: &[Coord]) -> (Board, Battlesnake) {
+        let you = Battlesnake {
+            id: String::from("test"),
+            name: String::from("test"),
+            health: 100,
+            length: 3,
+            body: vec![Coord::new(2, 5), Coord::new(2, 4), Coord::new(2, 3)],
+            head: Coord::new(2, 5),
+            latency: String::from("test"),
+            shout: None,
+        };
+        let rival = Battlesnake {
+            id: String::from("rival"),
+            name: String::from("rival"),
+            length: rival.len() as i32,
+            body: rival.to_vec(),
+            head: rival[0],
+            ..you.clone()
+        };
+
+        let board = Board {
+            height: 10,
+            width: 10,
+            food: vec![Coord::new(0, 5), Coord::new(6, 5)],
+            snakes: vec![you.clone(), rival],
+            hazards: vec![],
+        };
+
+        (board, you)
+    }
+
+    #[test]
+    fn leave_contested_food() {
+        // A longer rival gets to the closest food at the same time as us.
+        let (board, you) = get_rival_board(&[
+            Coord::new(0, 7),
+            Coord::new(1, 7),
+            Coord::new(2, 7),
+            Coord::new(3, 7),
+        ]);
+        let rules = Rules::default();
+        let options: Vec<_> = move_validator::get_valid_moves(&board, &you, &rules)
+            .into_iter()
+            .collect();
+
+        assert_eq!(
+            get_next_step(&board, &you, &rules, &options, Hunger::Feed),
+            Some(Direction::Right(Coord::new(3, 5)))
+        );
+    }
+
+    #[test]
+    fn take_risks_when_hungry() {
+        // We get to the closest food just one turn ahead of a shorter rival.
+        let (board, you) = get_rival_board(&[Coord::new(0, 8), Coord::new(0, 9)]);
+        let rules = Rules::default();
+        let options: Vec<_> = move_validator::get_valid_moves(&board, &you, &rules)
+            .into_iter()
+            .collect();
+
+        assert_eq!(
+            get_next_step(&board, &you, &rules, &options, Hunger::Feed),
+            Some(Direction::Right(Coord::new(3, 5)))
+        );
+
+        let hungry = Battlesnake { health: 5, ..you };
+
+        assert_eq!(
+            get_next_step(&board, &hungry, &rules, &options, Hunger::Feed),
+            Some(Direction::Left(Coord::new(1, 5)))
+        );
+    }
+
+    #[test]
+    fn no_step_without_food() {
+        let (mut board, you) = get_rival_board(&[Coord::new(0, 8), Coord::new(0, 9)]);
+        let rules = Rules::default();
+        let options: Vec<_> = move_validator::get_valid_moves(&board, &you, &rules)
+            .into_iter()
+            .collect();
+
+        assert_eq!(get_next_step(&board, &you, &rules, &[], Hunger::Feed), None);
+
+        // Under the head of the rival.
+        board.food = vec![Coord::new(0, 8)];
+        assert_eq!(
+            get_next_step(&board, &you, &rules, &options, Hunger::Feed),
+            None
+        );
+
+        board.food.clear();
+        assert_eq!(
+            get_next_step(&board, &you, &rules, &options, Hunger::Feed),
+            None
+        );
+    }
+
+    #[test]
+    fn no_step_to_lost_food() {
+        // The rival is closer to the only food.
+        let (mut board, you) = get_rival_board(&[
+            Coord::new(0, 7),
+            Coord::new(1, 7),
+            Coord::new(2, 7),
+            Coord::new(3, 7),
+        ]);
+        board.food = vec![Coord::new(0, 6)];
+        let rules = Rules::default();
+        let options: Vec<_> = move_validator::get_valid_moves(&board, &you, &rules)
+            .into_iter()
+            .collect();
+
+        assert_eq!(
+            get_next_step(&board, &you, &rules, &options, Hunger::Feed),
+            None
+        );
+    }
+
+    #[test]
+    fn risk_head_to_head_only_to_feed() {
+        // An even rival gets to the only food at the same time.
+        let (mut board, you) =
+            get_rival_board(&[Coord::new(0, 7), Coord::new(1, 7), Coord::new(2, 7)]);
+        board.food = vec![Coord::new(0, 5)];
+        let rules = Rules::default();
+        let options: Vec<_> = move_validator::get_valid_moves(&board, &you, &rules)
+            .into_iter()
+            .collect();
+
+        assert_eq!(
+            get_next_step(&board, &you, &rules, &options, Hunger::Feed),
+            Some(Direction::Left(Coord::new(1, 5)))
+        );
+        assert_eq!(
+            get_next_step(&board, &you, &rules, &options, Hunger::Grow),
+            None
+        );
+    }
+
+    #[test]
+    fn never_race_a_longer_rival() {
+        // A longer rival gets to the only food at the same time, even if we
+        // are starving.
+        let (mut board, you) = get_rival_board(&[
+            Coord::new(0, 7),
+            Coord::new(1, 7),
+            Coord::new(2, 7),
+            Coord::new(3, 7),
+            Coord::new(4, 7),
+        ]);
+        board.food = vec![Coord::new(0, 5)];
+        let you = Battlesnake { health: 10, ..you };
+        let rules = Rules::default();
+        let options: Vec<_> = move_validator::get_valid_moves(&board, &you, &rules)
+            .into_iter()
+            .collect();
 
-        assert_eq!(next_step, Direction::Right(Coord::new(6, 4)))
+        assert_eq!(
+            get_next_step(&board, &you, &rules, &options, Hunger::Feed),
+            None
+        );
     }
 }
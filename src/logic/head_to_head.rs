@@ -0,0 +1,143 @@
+use std::cmp::Ordering;
+
+use crate::domain::{Battlesnake, Board, Coord};
+
+use super::rules::Rules;
+
+// How it ends if other heads move to the same cell as ours, from best to
+// worst.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Collision {
+    // Every snake that can get there is shorter.
+    Win,
+    // The longest snake that can get there is as long as us.
+    MutualDeath,
+    // A longer snake can get there.
+    Loss,
+}
+
+// Worst collision moving to the cell can lead to, None if no other head can
+// get there next turn.
+pub fn get_collision(
+    cell: &Coord,
+    board: &Board,
+    you: &Battlesnake,
+    rules: &Rules,
+) -> Option<Collision> {
+    board
+        .snakes
+        .iter()
+        .filter(|snake| snake.id != you.id)
+        .filter(|snake| {
+            rules
+                .topology
+                .neighbours(board, &snake.head)
+                .iter()
+                .any(|dir| dir.get_coord() == cell)
+        })
+        .map(|snake| match snake.length.cmp(&you.length) {
+            Ordering::Less => Collision::Win,
+            Ordering::Equal => Collision::MutualDeath,
+            Ordering::Greater => Collision::Loss,
+        })
+        .max()
+}
+
+// Whether to risk the collision. Trading heads with an even snake takes a
+// rival out along with us, which only pays off when we're behind in a game
+// with more than one of them.
+pub fn is_acceptable(collision: Option<Collision>, board: &Board, you: &Battlesnake) -> bool {
+    match collision {
+        None | Some(Collision::Win) => true,
+        Some(Collision::MutualDeath) => is_behind(board, you),
+        Some(Collision::Loss) => false,
+    }
+}
+
+fn is_behind(board: &Board, you: &Battlesnake) -> bool {
+    let mut enemies = board.snakes.iter().filter(|snake| snake.id != you.id);
+
+    enemies.clone().count() > 1 && enemies.any(|snake| snake.length > you.length)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logic::fixtures::{get_board, get_snake};
+
+    fn get_you() -> Battlesnake {
+        get_snake(
+            "you",
+            &[Coord::new(4, 4), Coord::new(4, 3), Coord::new(4, 2)],
+        )
+    }
+
+    #[test]
+    fn classify_collisions() {
+        let you = get_you();
+        let shorter = get_snake("shorter", &[Coord::new(6, 4), Coord::new(7, 4)]);
+        let even = get_snake(
+            "even",
+            &[Coord::new(4, 6), Coord::new(4, 7), Coord::new(4, 8)],
+        );
+        let board = get_board(10, vec![you.clone(), shorter, even], &[]);
+        let rules = Rules::default();
+
+        assert_eq!(
+            get_collision(&Coord::new(5, 4), &board, &you, &rules),
+            Some(Collision::Win)
+        );
+        assert_eq!(
+            get_collision(&Coord::new(4, 5), &board, &you, &rules),
+            Some(Collision::MutualDeath)
+        );
+        assert_eq!(get_collision(&Coord::new(3, 4), &board, &you, &rules), None);
+    }
+
+    #[test]
+    fn longest_snake_decides() {
+        let you = get_you();
+        let shorter = get_snake("shorter", &[Coord::new(6, 4), Coord::new(7, 4)]);
+        let longer = get_snake(
+            "longer",
+            &[
+                Coord::new(5, 5),
+                Coord::new(5, 6),
+                Coord::new(5, 7),
+                Coord::new(5, 8),
+            ],
+        );
+        let board = get_board(10, vec![you.clone(), shorter, longer], &[]);
+
+        assert_eq!(
+            get_collision(&Coord::new(5, 4), &board, &you, &Rules::default()),
+            Some(Collision::Loss)
+        );
+    }
+
+    #[test]
+    fn trade_heads_when_behind() {
+        let you = get_you();
+        let even = get_snake(
+            "even",
+            &[Coord::new(4, 6), Coord::new(4, 7), Coord::new(4, 8)],
+        );
+        let longer = get_snake(
+            "longer",
+            &[
+                Coord::new(0, 0),
+                Coord::new(1, 0),
+                Coord::new(2, 0),
+                Coord::new(3, 0),
+            ],
+        );
+        let collision = Some(Collision::MutualDeath);
+
+        let duel = get_board(10, vec![you.clone(), even.clone()], &[]);
+        assert!(!is_acceptable(collision, &duel, &you));
+
+        let behind = get_board(10, vec![you.clone(), even, longer], &[]);
+        assert!(is_acceptable(collision, &behind, &you));
+        assert!(!is_acceptable(Some(Collision::Loss), &behind, &you));
+    }
+}
@@ -0,0 +1,189 @@
+use crate::domain::{Battlesnake, Board, RulesetSettings};
+
+use super::{food_finder::get_turns, rules::Rules};
+
+// Health we keep on top of what it takes to get to the nearest food.
+const FEED_MARGIN: i32 = 10;
+
+// Longest we expect to wait for food to show up when it seldom spawns.
+const MAX_WAIT: i32 = 50;
+
+// Segments we want over the longest other snake before we stop growing.
+const GROW_LEAD: usize = 2;
+
+// How badly we want food this turn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hunger {
+    // Running out of health, eat even if it's risky.
+    Feed,
+    // Too short to push the others around, eat what's safe to eat.
+    Grow,
+    // Long and healthy enough, stay out of trouble.
+    Idle,
+}
+
+impl Hunger {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Hunger::Feed => "feed",
+            Hunger::Grow => "grow",
+            Hunger::Idle => "idle",
+        }
+    }
+}
+
+pub fn get_hunger(
+    board: &Board,
+    you: &Battlesnake,
+    rules: &Rules,
+    settings: &RulesetSettings,
+) -> Hunger {
+    let turns = get_turns(board, rules, &you.head);
+    // Food may show up anywhere, half way across the board on average.
+    let nearest = board
+        .food
+        .iter()
+        .filter(|food| rules.topology.contains(board, food))
+        .filter_map(|food| turns[food.y as usize][food.x as usize])
+        .min()
+        .map_or((board.width + board.height) as i32 / 2, |turns| {
+            turns as i32
+        });
+
+    if you.health <= nearest + FEED_MARGIN + get_wait(settings) {
+        return Hunger::Feed;
+    }
+
+    let longest = board
+        .snakes
+        .iter()
+        .filter(|snake| snake.id != you.id)
+        .map(|snake| snake.body.len())
+        .max();
+
+    match longest {
+        Some(longest) if you.body.len() < longest + GROW_LEAD => Hunger::Grow,
+        _ => Hunger::Idle,
+    }
+}
+
+// Turns we may have to wait for new food if the one on the board runs out.
+// The minimum food comes back right away, the rest takes its chances.
+fn get_wait(settings: &RulesetSettings) -> i32 {
+    if settings.minimum_food > 0 {
+        return 0;
+    }
+
+    match settings.food_spawn_chance {
+        chance if chance > 0 => (100 / chance).min(MAX_WAIT),
+        _ => MAX_WAIT,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        domain::Coord,
+        logic::fixtures::{get_board, get_snake, get_snake_with_health},
+    };
+
+    fn get_rival_board(you: &Battlesnake, rival_length: i32) -> Board {
+        let rival: Vec<Coord> = (0..rival_length).map(|x| Coord::new(x, 10)).collect();
+
+        get_board(
+            11,
+            vec![you.clone(), get_snake("rival", &rival)],
+            &[Coord::new(5, 0)],
+        )
+    }
+
+    fn get_settings(food_spawn_chance: i32, minimum_food: i32) -> RulesetSettings {
+        RulesetSettings {
+            food_spawn_chance,
+            minimum_food,
+            ..RulesetSettings::default()
+        }
+    }
+
+    fn get_you(health: i32) -> Battlesnake {
+        get_snake_with_health(
+            "you",
+            &[
+                Coord::new(5, 5),
+                Coord::new(5, 6),
+                Coord::new(5, 7),
+                Coord::new(5, 8),
+            ],
+            health,
+        )
+    }
+
+    #[test]
+    fn feed_when_low() {
+        let you = get_you(14);
+        let board = get_rival_board(&you, 2);
+
+        // Five turns away from food.
+        assert_eq!(
+            get_hunger(&board, &you, &Rules::default(), &get_settings(15, 1)),
+            Hunger::Feed
+        );
+        assert_eq!(
+            get_hunger(
+                &board,
+                &get_you(16),
+                &Rules::default(),
+                &get_settings(15, 1)
+            ),
+            Hunger::Idle
+        );
+    }
+
+    #[test]
+    fn grow_when_short() {
+        let you = get_you(80);
+        let rules = Rules::default();
+        let settings = get_settings(15, 1);
+
+        assert_eq!(
+            get_hunger(&get_rival_board(&you, 2), &you, &rules, &settings),
+            Hunger::Idle
+        );
+        assert_eq!(
+            get_hunger(&get_rival_board(&you, 3), &you, &rules, &settings),
+            Hunger::Grow
+        );
+    }
+
+    #[test]
+    fn feed_earlier_when_food_is_scarce() {
+        let you = get_you(30);
+        let mut board = get_rival_board(&you, 2);
+        let rules = Rules::default();
+
+        assert_eq!(
+            get_hunger(&board, &you, &rules, &get_settings(15, 1)),
+            Hunger::Idle
+        );
+        assert_eq!(
+            get_hunger(&board, &you, &rules, &get_settings(1, 0)),
+            Hunger::Feed
+        );
+
+        // Nothing to eat, but more is coming right away.
+        board.food.clear();
+        assert_eq!(
+            get_hunger(&board, &you, &rules, &get_settings(0, 1)),
+            Hunger::Idle
+        );
+        assert_eq!(
+            get_hunger(&board, &you, &rules, &get_settings(5, 1)),
+            Hunger::Idle
+        );
+        assert_eq!(
+            get_hunger(&board, &you, &rules, &get_settings(5, 0)),
+            Hunger::Feed
+        );
+    }
+}
@@ -0,0 +1,365 @@
+use std::{collections::HashMap, time::Instant};
+
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+use crate::domain::Direction;
+
+use super::{bitboard::BitBoard, hunger::Hunger, rules::Rules};
+
+// Exploration constant of the UCT formula.
+const EXPLORATION: f64 = std::f64::consts::SQRT_2;
+
+// Turns played by a rollout before scoring whoever is still alive.
+const ROLLOUT_DEPTH: u32 = 30;
+
+// How one of our root moves did during the search.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MoveStats {
+    pub direction: Direction,
+    pub visits: u32,
+    pub win_rate: f64,
+}
+
+// Statistics of every move of one snake on a node. Each snake picks its own
+// move from them, regardless of what the others do (decoupled UCT).
+#[derive(Debug)]
+struct SnakeStats {
+    snake: usize,
+    moves: Vec<Direction>,
+    visits: Vec<u32>,
+    rewards: Vec<f64>,
+}
+
+impl SnakeStats {
+    fn new(snake: usize, moves: Vec<Direction>) -> Self {
+        SnakeStats {
+            snake,
+            visits: vec![0; moves.len()],
+            rewards: vec![0.0; moves.len()],
+            moves,
+        }
+    }
+
+    fn select(&self, total: u32) -> usize {
+        let ucb = |i: usize| match self.visits[i] {
+            0 => f64::INFINITY,
+            visits => {
+                self.rewards[i] / visits as f64
+                    + EXPLORATION * ((total as f64).ln() / visits as f64).sqrt()
+            }
+        };
+
+        (0..self.moves.len())
+            .max_by(|a, b| ucb(*a).total_cmp(&ucb(*b)))
+            .unwrap_or(0)
+    }
+}
+
+#[derive(Debug)]
+struct Node {
+    board: BitBoard,
+    visits: u32,
+    snakes: Vec<SnakeStats>,
+    // Index of the child reached with each joint move, a move index per snake.
+    children: HashMap<Vec<usize>, usize>,
+}
+
+// Monte Carlo tree search over simultaneous moves, meant for games with
+// many snakes where minimax doesn't scale.
+pub struct Mcts {
+    you: usize,
+    rules: Rules,
+    deadline: Instant,
+    rng: StdRng,
+    hunger: Hunger,
+}
+
+impl Mcts {
+    pub fn new(you: usize, rules: Rules, deadline: Instant, seed: u64) -> Self {
+        Mcts {
+            you,
+            rules,
+            deadline,
+            rng: StdRng::seed_from_u64(seed),
+            hunger: Hunger::Idle,
+        }
+    }
+
+    // Rewards our surviving rollouts by how well they feed or grow us, when
+    // that's what we're after.
+    pub fn with_hunger(mut self, hunger: Hunger) -> Self {
+        self.hunger = hunger;
+        self
+    }
+
+    // Runs until the deadline or `max_iterations`, and reports how each of
+    // our `options` did, most visited first.
+    pub fn search(
+        &mut self,
+        board: &BitBoard,
+        options: &[Direction],
+        max_iterations: u32,
+    ) -> Vec<MoveStats> {
+        let mut tree = vec![self.get_node(board.clone(), Some(options))];
+        let mut iterations = 0;
+
+        while iterations < max_iterations && Instant::now() < self.deadline {
+            self.iterate(&mut tree);
+            iterations += 1;
+        }
+
+        let mut stats: Vec<MoveStats> = tree[0]
+            .snakes
+            .iter()
+            .find(|snake| snake.snake == self.you)
+            .map(|snake| {
+                (0..snake.moves.len())
+                    .map(|i| MoveStats {
+                        direction: snake.moves[i],
+                        visits: snake.visits[i],
+                        win_rate: match snake.visits[i] {
+                            0 => 0.0,
+                            visits => snake.rewards[i] / visits as f64,
+                        },
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        stats.sort_by_key(|stat| std::cmp::Reverse(stat.visits));
+        stats
+    }
+
+    fn iterate(&mut self, tree: &mut Vec<Node>) {
+        let mut path: Vec<(usize, Vec<usize>)> = vec![];
+        let mut current = 0;
+
+        // Selection and expansion.
+        let rewards = loop {
+            let node = &tree[current];
+
+            if self.is_terminal(&node.board) {
+                break self.get_rewards(&node.board);
+            }
+
+            let joint: Vec<usize> = node
+                .snakes
+                .iter()
+                .map(|snake| snake.select(node.visits))
+                .collect();
+
+            path.push((current, joint.clone()));
+
+            if let Some(child) = node.children.get(&joint) {
+                current = *child;
+                continue;
+            }
+
+            let mut moves = vec![None; node.board.snakes.len()];
+
+            for (snake, i) in node.snakes.iter().zip(joint.iter()) {
+                moves[snake.snake] = Some(snake.moves[*i]);
+            }
+
+            let mut board = node.board.clone();
+            board.advance(&moves, &self.rules);
+            let rewards = self.rollout(&board);
+
+            tree.push(self.get_node(board, None));
+            let child = tree.len() - 1;
+            tree[current].children.insert(joint, child);
+
+            break rewards;
+        };
+
+        // Backpropagation.
+        for (index, joint) in path {
+            let node = &mut tree[index];
+
+            node.visits += 1;
+
+            for (snake, i) in node.snakes.iter_mut().zip(joint) {
+                snake.visits[i] += 1;
+                snake.rewards[i] += rewards[snake.snake];
+            }
+        }
+    }
+
+    // Plays random but safe moves for everybody.
+    fn rollout(&mut self, board: &BitBoard) -> Vec<f64> {
+        let mut board = board.clone();
+
+        for _ in 0..ROLLOUT_DEPTH {
+            if self.is_terminal(&board) {
+                break;
+            }
+
+            let moves: Vec<Option<Direction>> = (0..board.snakes.len())
+                .map(|i| self.get_moves(&board, i).choose(&mut self.rng).copied())
+                .collect();
+
+            board.advance(&moves, &self.rules);
+        }
+
+        self.get_rewards(&board)
+    }
+
+    fn get_node(&self, board: BitBoard, options: Option<&[Direction]>) -> Node {
+        let snakes = board
+            .alive()
+            .map(|i| {
+                let moves = match options {
+                    Some(options) if i == self.you => options.to_vec(),
+                    _ => self.get_moves(&board, i),
+                };
+
+                SnakeStats::new(i, moves)
+            })
+            .collect();
+
+        Node {
+            board,
+            visits: 0,
+            snakes,
+            children: HashMap::new(),
+        }
+    }
+
+    // Safe moves of the snake, or every move if it's doomed anyway. Moves
+    // come in a stable order so seeded searches are reproducible.
+    fn get_moves(&self, board: &BitBoard, snake: usize) -> Vec<Direction> {
+        if !board.is_alive(snake) {
+            return vec![];
+        }
+
+        let moves = board.valid_moves(snake, &self.rules);
+
+        if moves.is_empty() {
+            let head = board.coord(board.snakes[snake].head());
+
+            return self.rules.topology.neighbours(board, &head).to_vec();
+        }
+
+        moves
+    }
+
+    fn is_terminal(&self, board: &BitBoard) -> bool {
+        board.alive().count() <= 1 || !board.is_alive(self.you)
+    }
+
+    // Survivors share the win, the dead get nothing. Indexed by snake.
+    fn get_rewards(&self, board: &BitBoard) -> Vec<f64> {
+        let reward = 1.0 / board.alive().count().max(1) as f64;
+
+        (0..board.snakes.len())
+            .map(|i| match board.is_alive(i) {
+                true if i == self.you => reward * self.get_appetite(board),
+                true => reward,
+                false => 0.0,
+            })
+            .collect()
+    }
+
+    // Share of our reward kept at the end of a rollout, by how hungry we are.
+    fn get_appetite(&self, board: &BitBoard) -> f64 {
+        let you = &board.snakes[self.you];
+
+        match self.hunger {
+            Hunger::Feed => 0.5 + 0.5 * you.health as f64 / 100.0,
+            Hunger::Grow => {
+                let longest = board
+                    .alive()
+                    .filter(|i| *i != self.you)
+                    .map(|i| board.snakes[i].len())
+                    .max()
+                    .unwrap_or(0);
+
+                0.5 + 0.5 * (you.len() as f64 / longest.max(1) as f64).min(1.0)
+            }
+            Hunger::Idle => 1.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        domain::Coord,
+        logic::fixtures::{get_board, get_snake},
+    };
+
+    fn get_mcts() -> Mcts {
+        Mcts::new(
+            0,
+            Rules::default(),
+            Instant::now() + Duration::from_secs(10),
+            7,
+        )
+    }
+
+    #[test]
+    fn report_root_stats() {
+        let board = get_board(
+            7,
+            vec![
+                get_snake("you", &[Coord::new(1, 1), Coord::new(1, 0)]),
+                get_snake("a", &[Coord::new(5, 5), Coord::new(5, 6)]),
+                get_snake("b", &[Coord::new(1, 5), Coord::new(1, 6)]),
+            ],
+            &[],
+        );
+        let options = vec![
+            Direction::Up(Coord::new(1, 2)),
+            Direction::Right(Coord::new(2, 1)),
+        ];
+
+        let stats = get_mcts().search(&BitBoard::new(&board).unwrap(), &options, 200);
+
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats.iter().map(|s| s.visits).sum::<u32>(), 200);
+        assert!(stats.iter().all(|s| (0.0..=1.0).contains(&s.win_rate)));
+    }
+
+    #[test]
+    fn avoid_dead_end() {
+        // Going left walks into the corner, walled by the enemy for longer
+        // than we can wait.
+        let board = get_board(
+            7,
+            vec![
+                get_snake(
+                    "you",
+                    &[Coord::new(2, 0), Coord::new(3, 0), Coord::new(4, 0)],
+                ),
+                get_snake(
+                    "enemy",
+                    &[
+                        Coord::new(2, 4),
+                        Coord::new(2, 3),
+                        Coord::new(1, 3),
+                        Coord::new(1, 2),
+                        Coord::new(1, 1),
+                        Coord::new(0, 1),
+                        Coord::new(0, 2),
+                        Coord::new(0, 3),
+                        Coord::new(0, 4),
+                        Coord::new(0, 5),
+                    ],
+                ),
+            ],
+            &[],
+        );
+        let options = vec![
+            Direction::Left(Coord::new(1, 0)),
+            Direction::Up(Coord::new(2, 1)),
+        ];
+
+        let stats = get_mcts().search(&BitBoard::new(&board).unwrap(), &options, 500);
+
+        assert_eq!(stats[0].direction, Direction::Up(Coord::new(2, 1)));
+        assert!(stats[0].win_rate > stats[1].win_rate);
+    }
+}
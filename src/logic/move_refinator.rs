@@ -1,78 +1,63 @@
+// The original move logic predates clippy, its idioms are kept as they are.
+#![allow(clippy::bool_assert_comparison, clippy::ptr_arg)]
+
 use crate::domain::{Battlesnake, Board, Coord, Direction};
 
+use super::{
+    head_to_head::{get_collision, is_acceptable, Collision},
+    rules::Rules,
+};
+
+// A move where we would win the head on collision, if any.
 pub fn recommend_move<'a>(
     options: &'a Vec<Direction>,
     you: &Battlesnake,
     board: &Board,
+    rules: &Rules,
 ) -> Option<&'a Direction> {
-    let movement_array = vec![(0, 1), (1, 0), (0, -1), (-1, 0)]; // All the available x, y moves
-
-    for option in options {
-        let Coord { x, y } = option.get_coord();
-
-        for movement in movement_array.iter() {
-            let possible_enemy = Coord::new(movement.0 + x, movement.1 + y);
-            let enemy = get_snake(&possible_enemy, board, you);
-
-            if let Some(snake) = enemy {
-                if you.length > snake.length && snake.head == possible_enemy {
-                    return Some(option);
-                }
-            }
-        }
-    }
-
-    None
+    options
+        .iter()
+        .find(|option| get_collision(option.get_coord(), board, you, rules) == Some(Collision::Win))
 }
 
 pub fn refined_movements(
     options: &Vec<Direction>,
     board: &Board,
     you: &Battlesnake,
+    rules: &Rules,
 ) -> Vec<Direction> {
     options
         .iter()
-        .filter(|opt| avoid_loser_hits(opt.get_coord(), board, you))
+        .filter(|opt| avoid_loser_hits(opt.get_coord(), board, you, rules))
         .cloned()
         .collect()
 }
 
-fn avoid_loser_hits(next_movement: &Coord, board: &Board, you: &Battlesnake) -> bool {
-    let movement_array = vec![(0, 1), (1, 0), (0, -1), (-1, 0)]; // All the available x, y moves
-
-    for movement in movement_array {
-        let possible_enemy = Coord::new(movement.0 + next_movement.x, movement.1 + next_movement.y);
-        let enemy = get_snake(&possible_enemy, board, you);
-
-        if enemy.is_some() {
-            return false;
-        }
-    }
-
-    true
-}
+// Keep the moves that burn the least health in hazards.
+pub fn avoid_hazards(options: &[Direction], board: &Board, rules: &Rules) -> Vec<Direction> {
+    let cheapest = options
+        .iter()
+        .map(|opt| rules.hazard_cost(board, opt.get_coord()))
+        .min();
 
-fn remove_you_from_snakes(you: &Battlesnake, snakes: &Vec<Battlesnake>) -> Vec<Battlesnake> {
-    snakes
+    options
         .iter()
-        .filter(|snake| snake.head != you.head)
+        .filter(|opt| Some(rules.hazard_cost(board, opt.get_coord())) == cheapest)
         .cloned()
         .collect()
 }
 
-fn get_snake(point: &Coord, board: &Board, you: &Battlesnake) -> Option<Battlesnake> {
-    for enemy in remove_you_from_snakes(you, &board.snakes) {
-        let is_an_enemy = enemy.body[..enemy.body.len() - 1]
-            .iter()
-            .position(|x| x == point)
-            .is_some();
-
-        if is_an_enemy {
-            return Some(enemy.clone());
-        }
-    }
+// Whether the head on collisions we could get into moving there are worth
+// it.
+fn avoid_loser_hits(
+    next_movement: &Coord,
+    board: &Board,
+    you: &Battlesnake,
+    rules: &Rules,
+) -> bool {
+    let collision = get_collision(next_movement, board, you, rules);
 
-    None
+    is_acceptable(collision, board, you)
 }
 
 #[cfg(test)]
@@ -125,7 +110,7 @@ mod tests {
 
         let next_step = Coord::new(7, 3);
 
-        let response = avoid_loser_hits(&next_step, &board, &you);
+        let response = avoid_loser_hits(&next_step, &board, &you, &Rules::default());
 
         assert_eq!(response, false)
     }
@@ -149,7 +134,7 @@ mod tests {
 
         let next_step = Coord::new(4, 4);
 
-        let response = avoid_loser_hits(&next_step, &board, &you);
+        let response = avoid_loser_hits(&next_step, &board, &you, &Rules::default());
 
         assert_eq!(response, true);
     }
@@ -167,7 +152,7 @@ mod tests {
             Direction::Left(Coord::new(6, 3)),
         ];
 
-        let response = recommend_move(&options, &you, &board);
+        let response = recommend_move(&options, &you, &board, &Rules::default());
 
         assert_eq!(response, Some(&Direction::Left(Coord::new(6, 3))));
     }
@@ -185,7 +170,7 @@ mod tests {
             Direction::Left(Coord::new(7, 2)),
         ];
 
-        let response = recommend_move(&options, &you, &board);
+        let response = recommend_move(&options, &you, &board, &Rules::default());
 
         assert_eq!(response, None);
     }
@@ -213,8 +198,26 @@ mod tests {
             Direction::Right(Coord::new(5, 3)),
         ];
 
-        let reponse = refined_movements(&options, &board, &you);
+        let reponse = refined_movements(&options, &board, &you, &Rules::default());
 
         assert_eq!(reponse, vec![Direction::Up(Coord::new(4, 4))]);
     }
+
+    #[test]
+    fn get_cheapest_hazard_moves() {
+        let (_, mut board, _) = get_mock_data(
+            &vec![Coord::new(0, 0), Coord::new(0, 1)],
+            &vec![Coord::new(5, 5), Coord::new(5, 4)],
+        );
+        board.hazards = vec![Coord::new(5, 6), Coord::new(4, 5), Coord::new(4, 5)];
+
+        let options = vec![
+            Direction::Up(Coord::new(5, 6)),
+            Direction::Left(Coord::new(4, 5)),
+        ];
+
+        let response = avoid_hazards(&options, &board, &Rules::default());
+
+        assert_eq!(response, vec![Direction::Up(Coord::new(5, 6))]);
+    }
 }
@@ -1,70 +1,98 @@
+// The original move logic predates clippy, its idioms are kept as they are.
+#![allow(clippy::bool_assert_comparison, clippy::ptr_arg, clippy::useless_vec)]
+
 use std::collections::HashSet;
 
 use crate::domain::{Battlesnake, Board, Coord, Direction};
 
-pub fn get_valid_moves(board: &Board, you: &Battlesnake) -> HashSet<Direction> {
-    let head_x = you.head.x;
-    let head_y = you.head.y;
+use super::{
+    bitboard::{BitBoard, BitSet},
+    rules::Rules,
+};
+
+pub fn get_valid_moves(board: &Board, you: &Battlesnake, rules: &Rules) -> HashSet<Direction> {
+    match BitBoard::new(board) {
+        Some(bitboard) => get_bitboard_moves(&bitboard, you, rules)
+            .into_iter()
+            .collect(),
+        // The engine never offers boards that big.
+        None => HashSet::new(),
+    }
+}
 
-    let movement_array = vec![
-        Direction::Up(Coord::new(head_x, head_y + 1)),
-        Direction::Right(Coord::new(head_x + 1, head_y)),
-        Direction::Down(Coord::new(head_x, head_y - 1)),
-        Direction::Left(Coord::new(head_x - 1, head_y)),
-    ]; // All the available x, y moves
+// Same as `get_valid_moves`, for callers checking many snakes against the
+// same board. The snake doesn't need to be on the board, its own body is
+// taken from `you`. Moves keep the order of the neighbours.
+pub fn get_bitboard_moves(board: &BitBoard, you: &Battlesnake, rules: &Rules) -> Vec<Direction> {
+    let body = get_body(board, you, rules);
+    let movement_array = rules.topology.neighbours(board, &you.head); // All the available x, y moves
 
     movement_array
         .into_iter()
-        .filter(|dir| is_valid_move(board, you, dir.get_coord()))
+        .filter(|dir| is_valid_move(board, you, &body, rules, dir.get_coord()))
         .collect()
 }
 
-fn is_valid_move(board: &Board, you: &Battlesnake, next_movement: &Coord) -> bool {
-    is_inside_bounds(board, next_movement)
-        && is_not_own_body(you, next_movement)
-        && is_not_an_enemy(board, next_movement)
+fn get_body(board: &BitBoard, you: &Battlesnake, rules: &Rules) -> BitSet {
+    let mut body = BitSet::default();
+
+    for segment in rules
+        .solid_body(&you.body)
+        .iter()
+        .filter(|segment| is_inside_bounds(board, segment))
+    {
+        body.set(board.index(segment));
+    }
+
+    body
 }
 
-fn is_inside_bounds(board: &Board, next_movement: &Coord) -> bool {
+fn is_valid_move(
+    board: &BitBoard,
+    you: &Battlesnake,
+    body: &BitSet,
+    rules: &Rules,
+    next_movement: &Coord,
+) -> bool {
+    if !is_inside_bounds(board, next_movement) {
+        return false;
+    }
+
+    let cell = board.index(next_movement);
+
+    is_not_own_body(body, cell)
+        && is_not_an_enemy(board, rules, cell)
+        && (is_not_a_hazard(board, cell) || is_survivable_hazard(board, you, rules, cell))
+}
+
+fn is_inside_bounds(board: &BitBoard, next_movement: &Coord) -> bool {
     next_movement.x >= 0
         && (next_movement.x as u32) < board.width
         && next_movement.y >= 0
         && (next_movement.y as u32) < (board.height)
 }
 
-fn is_not_own_body(you: &Battlesnake, next_movement: &Coord) -> bool {
-    you.body[..you.body.len() - 1]
-        .iter()
-        .position(|x| x == next_movement)
-        .is_none()
+fn is_not_own_body(body: &BitSet, cell: usize) -> bool {
+    !body.get(cell)
 }
 
-fn is_not_an_enemy(board: &Board, next_movement: &Coord) -> bool {
-    for enemy in &board.snakes {
-        let is_an_enemy = enemy.body[..enemy.body.len() - 1]
-            .iter()
-            .position(|x| x == next_movement)
-            .is_some();
-
-        if is_an_enemy {
-            return false;
-        }
-    }
+fn is_not_an_enemy(board: &BitBoard, rules: &Rules, cell: usize) -> bool {
+    !board.is_blocked(cell, rules)
+}
 
-    true
+fn is_not_a_hazard(board: &BitBoard, cell: usize) -> bool {
+    !board.hazards.get(cell)
 }
 
-fn is_not_a_hazard(board: &Board, next_movement: &Coord) -> bool {
-    board
-        .hazards
-        .iter()
-        .position(|x| x == next_movement)
-        .is_none()
+// Eating on a hazard cancels its damage for that turn.
+fn is_survivable_hazard(board: &BitBoard, you: &Battlesnake, rules: &Rules, cell: usize) -> bool {
+    board.food.get(cell) || you.health - 1 - board.hazard_cost(cell, rules) > 0
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::domain::Ruleset;
 
     struct TestCase {
         body: Vec<Coord>,
@@ -168,7 +196,8 @@ mod tests {
                     &test_case.hazards,
                 );
 
-                let is_valid = is_inside_bounds(&board, &test_case.next_move);
+                let bitboard = BitBoard::new(&board).unwrap();
+                let is_valid = is_inside_bounds(&bitboard, &test_case.next_move);
                 assert_eq!(false, is_valid);
             }
         }
@@ -200,7 +229,8 @@ mod tests {
                     &test_case.hazards,
                 );
 
-                let is_valid = is_inside_bounds(&board, &test_case.next_move);
+                let bitboard = BitBoard::new(&board).unwrap();
+                let is_valid = is_inside_bounds(&bitboard, &test_case.next_move);
                 assert_eq!(true, is_valid);
             }
         }
@@ -235,14 +265,16 @@ mod tests {
             ];
 
             for test_case in test_cases.iter() {
-                let (_, battlesnake) = setup_game(
+                let (board, battlesnake) = setup_game(
                     &test_case.body,
                     test_case.head,
                     test_case.snakes.clone(),
                     &test_case.hazards,
                 );
 
-                let is_valid = is_not_own_body(&battlesnake, &test_case.next_move);
+                let bitboard = BitBoard::new(&board).unwrap();
+                let body = get_body(&bitboard, &battlesnake, &Rules::default());
+                let is_valid = is_not_own_body(&body, bitboard.index(&test_case.next_move));
                 assert_eq!(false, is_valid);
             }
         }
@@ -265,14 +297,16 @@ mod tests {
             )];
 
             for test_case in test_cases.iter() {
-                let (_, battlesnake) = setup_game(
+                let (board, battlesnake) = setup_game(
                     &test_case.body,
                     test_case.head,
                     test_case.snakes.clone(),
                     &test_case.hazards,
                 );
 
-                let is_valid = is_not_own_body(&battlesnake, &test_case.next_move);
+                let bitboard = BitBoard::new(&board).unwrap();
+                let body = get_body(&bitboard, &battlesnake, &Rules::default());
+                let is_valid = is_not_own_body(&body, bitboard.index(&test_case.next_move));
                 assert_eq!(true, is_valid);
             }
         }
@@ -308,7 +342,9 @@ mod tests {
                     &test_case.hazards,
                 );
 
-                let is_valid = is_not_an_enemy(&board, &test_case.next_move);
+                let bitboard = BitBoard::new(&board).unwrap();
+                let cell = bitboard.index(&test_case.next_move);
+                let is_valid = is_not_an_enemy(&bitboard, &Rules::default(), cell);
                 assert_eq!(false, is_valid);
             }
         }
@@ -340,7 +376,9 @@ mod tests {
                     &test_case.hazards,
                 );
 
-                let is_valid = is_not_an_enemy(&board, &test_case.next_move);
+                let bitboard = BitBoard::new(&board).unwrap();
+                let cell = bitboard.index(&test_case.next_move);
+                let is_valid = is_not_an_enemy(&bitboard, &Rules::default(), cell);
                 assert_eq!(true, is_valid);
             }
         }
@@ -367,7 +405,8 @@ mod tests {
                     &test_case.hazards,
                 );
 
-                let is_valid = is_not_a_hazard(&board, &test_case.next_move);
+                let bitboard = BitBoard::new(&board).unwrap();
+                let is_valid = is_not_a_hazard(&bitboard, bitboard.index(&test_case.next_move));
                 assert_eq!(false, is_valid);
             }
         }
@@ -390,7 +429,8 @@ mod tests {
                     &test_case.hazards,
                 );
 
-                let is_valid = is_not_a_hazard(&board, &test_case.next_move);
+                let bitboard = BitBoard::new(&board).unwrap();
+                let is_valid = is_not_a_hazard(&bitboard, bitboard.index(&test_case.next_move));
                 assert_eq!(true, is_valid);
             }
         }
@@ -410,7 +450,7 @@ mod tests {
             ];
             let head = Coord::new(2, 2);
             let (board, battlesnake) = setup_game(&body, head, vec![], &vec![]);
-            let valid_moves = get_valid_moves(&board, &battlesnake);
+            let valid_moves = get_valid_moves(&board, &battlesnake, &Rules::default());
             let correct_answer: HashSet<Direction> = vec![
                 Direction::Left(Coord::new(1, 2)),
                 Direction::Up(Coord::new(2, 3)),
@@ -420,5 +460,86 @@ mod tests {
 
             assert_eq!(valid_moves, correct_answer);
         }
+
+        #[test]
+        fn should_get_survivable_hazards() {
+            let body = vec![Coord::new(2, 2), Coord::new(2, 1), Coord::new(2, 0)];
+            let head = Coord::new(2, 2);
+            let hazards = vec![
+                Coord::new(1, 2),
+                Coord::new(3, 2),
+                Coord::new(3, 2),
+                Coord::new(2, 3),
+            ];
+            let (board, battlesnake) = setup_game(&body, head, vec![], &hazards);
+            let valid_moves = get_valid_moves(&board, &battlesnake, &Rules::default());
+            let correct_answer: HashSet<Direction> = vec![
+                Direction::Left(Coord::new(1, 2)),
+                Direction::Up(Coord::new(2, 3)),
+            ]
+            .into_iter()
+            .collect();
+
+            assert_eq!(valid_moves, correct_answer);
+        }
+
+        #[test]
+        fn should_wrap_moves() {
+            let body = vec![Coord::new(0, 0), Coord::new(1, 0), Coord::new(2, 0)];
+            let head = Coord::new(0, 0);
+            let (board, battlesnake) = setup_game(&body, head, vec![], &vec![]);
+            let rules = Rules::new(&Ruleset::new("wrapped"));
+            let valid_moves = get_valid_moves(&board, &battlesnake, &rules);
+            let correct_answer: HashSet<Direction> = vec![
+                Direction::Up(Coord::new(0, 1)),
+                Direction::Down(Coord::new(0, 4)),
+                Direction::Left(Coord::new(4, 0)),
+            ]
+            .into_iter()
+            .collect();
+
+            assert_eq!(valid_moves, correct_answer);
+        }
+
+        #[test]
+        fn should_not_follow_tails_in_constrictor() {
+            let body = vec![
+                Coord::new(2, 2),
+                Coord::new(3, 2),
+                Coord::new(3, 1),
+                Coord::new(2, 1),
+            ];
+            let head = Coord::new(2, 2);
+            let enemy = Battlesnake {
+                id: String::from("enemy_id"),
+                name: String::from("enemy"),
+                health: 10,
+                body: vec![Coord::new(0, 3), Coord::new(1, 3), Coord::new(2, 3)],
+                head: Coord::new(0, 3),
+                length: 3,
+                latency: String::from(""),
+                shout: None,
+            };
+            let (board, battlesnake) = setup_game(&body, head, vec![enemy], &vec![]);
+
+            let valid_moves = get_valid_moves(&board, &battlesnake, &Rules::default());
+            let correct_answer: HashSet<Direction> = vec![
+                Direction::Left(Coord::new(1, 2)),
+                Direction::Up(Coord::new(2, 3)),
+                Direction::Down(Coord::new(2, 1)),
+            ]
+            .into_iter()
+            .collect();
+
+            assert_eq!(valid_moves, correct_answer);
+
+            let rules = Rules::new(&Ruleset::new("constrictor"));
+            let valid_moves = get_valid_moves(&board, &battlesnake, &rules);
+            let correct_answer: HashSet<Direction> = vec![Direction::Left(Coord::new(1, 2))]
+                .into_iter()
+                .collect();
+
+            assert_eq!(valid_moves, correct_answer);
+        }
     }
 }
@@ -0,0 +1,89 @@
+use crate::domain::{Board, Coord, GameMode, Ruleset};
+
+use super::topology::Topology;
+
+// Damage the engine uses when the ruleset doesn't say otherwise.
+const DEFAULT_HAZARD_DAMAGE: i32 = 14;
+
+// The parts of the ruleset the move logic cares about, cheap to copy
+// around while searching.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rules {
+    pub mode: GameMode,
+    pub topology: Topology,
+    pub hazard_damage: i32,
+}
+
+impl Rules {
+    pub fn new(ruleset: &Ruleset) -> Self {
+        Rules {
+            mode: ruleset.mode(),
+            topology: Topology::new(ruleset.mode()),
+            hazard_damage: ruleset
+                .settings
+                .hazard_damage_per_turn
+                .unwrap_or(DEFAULT_HAZARD_DAMAGE),
+        }
+    }
+
+    // Health lost for standing on the coord, on top of the regular point per
+    // turn. Stacked hazards show up as the same coord repeated in the board.
+    pub fn hazard_cost(&self, board: &Board, coord: &Coord) -> i32 {
+        let stack = board.hazards.iter().filter(|x| *x == coord).count() as i32;
+
+        stack * self.hazard_damage
+    }
+
+    // In constrictor games snakes grow every turn, so tails never move away.
+    pub fn tails_vacate(&self) -> bool {
+        self.mode != GameMode::Constrictor
+    }
+
+    // The segments still occupied after the snake moves.
+    pub fn solid_body<'a>(&self, body: &'a [Coord]) -> &'a [Coord] {
+        if self.tails_vacate() {
+            &body[..body.len() - 1]
+        } else {
+            body
+        }
+    }
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            mode: GameMode::Standard,
+            topology: Topology::Bounded,
+            hazard_damage: DEFAULT_HAZARD_DAMAGE,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_hazard_damage() {
+        let parse = |settings: &str| -> Ruleset {
+            serde_json::from_str(&format!(
+                r#"{{ "name": "royale", "version": "v1", "settings": {} }}"#,
+                settings
+            ))
+            .unwrap()
+        };
+
+        assert_eq!(
+            Rules::new(&parse(r#"{ "minimumFood": 1 }"#)).hazard_damage,
+            DEFAULT_HAZARD_DAMAGE
+        );
+        assert_eq!(
+            Rules::new(&parse(r#"{ "hazardDamagePerTurn": 0 }"#)).hazard_damage,
+            0
+        );
+        assert_eq!(
+            Rules::new(&Ruleset::new("royale")).hazard_damage,
+            DEFAULT_HAZARD_DAMAGE
+        );
+    }
+}
@@ -0,0 +1,536 @@
+use std::{cell::RefCell, time::Instant};
+
+use crate::domain::Direction;
+
+use super::{
+    bitboard::BitBoard,
+    hunger::Hunger,
+    rules::Rules,
+    territory::FOOD_WEIGHT,
+    transposition::{Bound, Entry, TranspositionTable},
+    zobrist,
+};
+
+const WIN: i32 = 1_000_000;
+const LOSS: i32 = -WIN;
+const DRAW: i32 = -WIN / 2;
+
+// Weight of every extra segment over the opponent in the default evaluation.
+const LENGTH_WEIGHT: i32 = 10;
+
+// Scores a board that is still being played, from the point of view of the
+// snake with the given index. Bigger is better.
+pub type Evaluate = fn(&BitBoard, usize, &Rules) -> i32;
+
+// Weight of our health in the evaluation when we need to feed.
+const HEALTH_WEIGHT: i32 = 2;
+
+// Territory and length advantage over the other snakes.
+pub fn evaluate(board: &BitBoard, you: usize, rules: &Rules) -> i32 {
+    get_advantage(board, you, rules, FOOD_WEIGHT)
+}
+
+// The evaluation following how badly we want food: feeding values our
+// health too, and idling leaves food out of the territories.
+pub fn get_evaluate(hunger: Hunger) -> Evaluate {
+    match hunger {
+        Hunger::Feed => evaluate_feeding,
+        Hunger::Grow => evaluate,
+        Hunger::Idle => evaluate_idling,
+    }
+}
+
+fn evaluate_feeding(board: &BitBoard, you: usize, rules: &Rules) -> i32 {
+    evaluate(board, you, rules) + HEALTH_WEIGHT * board.snakes[you].health
+}
+
+fn evaluate_idling(board: &BitBoard, you: usize, rules: &Rules) -> i32 {
+    get_advantage(board, you, rules, 0)
+}
+
+fn get_advantage(board: &BitBoard, you: usize, rules: &Rules, food_weight: usize) -> i32 {
+    let territories = board.territories(rules);
+
+    board
+        .alive()
+        .map(|i| {
+            let (cells, food) = territories[i];
+            let value =
+                (cells + food_weight * food) as i32 + LENGTH_WEIGHT * board.snakes[i].len() as i32;
+
+            if i == you {
+                value
+            } else {
+                -value
+            }
+        })
+        .sum()
+}
+
+// Depth limited alpha-beta search for one versus one games. Moves are
+// simultaneous, so the opponent is allowed to answer each of our moves
+// (paranoid search).
+pub struct Minimax {
+    you: usize,
+    rules: Rules,
+    evaluate: Evaluate,
+    deadline: Instant,
+    table: RefCell<TranspositionTable>,
+}
+
+impl Minimax {
+    pub fn new(you: usize, rules: Rules, evaluate: Evaluate, deadline: Instant) -> Self {
+        Minimax {
+            you,
+            rules,
+            evaluate,
+            deadline,
+            table: RefCell::default(),
+        }
+    }
+
+    // Search with the positions already known from previous turns.
+    pub fn with_table(mut self, table: TranspositionTable) -> Self {
+        self.table = RefCell::new(table);
+        self
+    }
+
+    // Gives back the table, to keep it for the next turn.
+    pub fn into_table(self) -> TranspositionTable {
+        self.table.into_inner()
+    }
+
+    // Best of our `options` looking `depth` turns ahead, or None if the
+    // deadline came first.
+    pub fn best_move(
+        &self,
+        board: &BitBoard,
+        options: &[Direction],
+        depth: u32,
+    ) -> Option<Direction> {
+        let depth = depth.max(1);
+        let key = zobrist::hash(board);
+        let mut alpha = LOSS - depth as i32 - 1;
+        let mut best = None;
+
+        let mut options = options.to_vec();
+        self.order_moves(key, &mut options);
+
+        for option in options.iter() {
+            let value = self.min_value(board, option, depth, alpha, WIN + depth as i32 + 1)?;
+
+            if best.is_none() || value > alpha {
+                alpha = value;
+                best = Some(*option);
+            }
+        }
+
+        if best.is_some() {
+            self.store(key, depth, Bound::Exact, alpha, best);
+        }
+
+        best
+    }
+
+    // Search one turn deeper at a time until the deadline, keeping the answer
+    // of the deepest search that finished, along with its depth.
+    pub fn iterative_best_move(
+        &self,
+        board: &BitBoard,
+        options: &[Direction],
+        max_depth: u32,
+    ) -> Option<(Direction, u32)> {
+        let mut best = None;
+
+        for depth in 1..=max_depth {
+            match self.best_move(board, options, depth) {
+                Some(dir) => best = Some((dir, depth)),
+                None => break,
+            }
+        }
+
+        best
+    }
+
+    fn max_value(
+        &self,
+        board: &BitBoard,
+        depth: u32,
+        mut alpha: i32,
+        mut beta: i32,
+    ) -> Option<i32> {
+        if Instant::now() >= self.deadline {
+            return None;
+        }
+
+        if let Some(value) = self.terminal_value(board, depth) {
+            return Some(value);
+        }
+
+        let key = zobrist::hash(board);
+        let (low, high) = (alpha, beta);
+        let known = self.table.borrow().get(key).copied();
+
+        // Reached before, through other moves or on a previous turn.
+        if let Some(entry) = known.filter(|entry| entry.depth >= depth) {
+            let value = from_table(entry.value, depth);
+
+            match entry.bound {
+                Bound::Exact => return Some(value),
+                Bound::Lower => alpha = alpha.max(value),
+                Bound::Upper => beta = beta.min(value),
+            }
+
+            if alpha >= beta {
+                return Some(value);
+            }
+        }
+
+        let mut options = board.valid_moves(self.you, &self.rules);
+
+        // No way out, whatever we do.
+        if options.is_empty() {
+            return Some(LOSS - depth as i32);
+        }
+
+        self.order_moves(key, &mut options);
+
+        let mut best = LOSS - depth as i32;
+        let mut best_move = None;
+
+        for option in options.iter() {
+            let value = self.min_value(board, option, depth, alpha, beta)?;
+
+            if best_move.is_none() || value > best {
+                best = value;
+                best_move = Some(*option);
+            }
+
+            alpha = alpha.max(value);
+
+            if best >= beta {
+                break;
+            }
+        }
+
+        let bound = if best <= low {
+            Bound::Upper
+        } else if best >= high {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        self.store(key, depth, bound, best, best_move);
+
+        Some(best)
+    }
+
+    // Best move found for the position last time goes first, it makes
+    // cut-offs come sooner.
+    fn order_moves(&self, key: u64, options: &mut [Direction]) {
+        let best = self.table.borrow().get(key).and_then(|entry| entry.best);
+
+        if let Some(first) = best.and_then(|best| options.iter().position(|dir| *dir == best)) {
+            options[..=first].rotate_right(1);
+        }
+    }
+
+    fn store(&self, key: u64, depth: u32, bound: Bound, value: i32, best: Option<Direction>) {
+        self.table.borrow_mut().insert(Entry {
+            key,
+            depth,
+            bound,
+            value: to_table(value, depth),
+            best,
+        });
+    }
+
+    // The opponent's best answer to our move.
+    fn min_value(
+        &self,
+        board: &BitBoard,
+        option: &Direction,
+        depth: u32,
+        alpha: i32,
+        mut beta: i32,
+    ) -> Option<i32> {
+        let mut worst = WIN + depth as i32;
+
+        for answer in self.opponent_moves(board) {
+            let mut moves = vec![None; board.snakes.len()];
+            moves[self.you] = Some(*option);
+
+            if let Some((opponent, dir)) = answer {
+                moves[opponent] = Some(dir);
+            }
+
+            let mut next = board.clone();
+            next.advance(&moves, &self.rules);
+
+            let value = self.max_value(&next, depth - 1, alpha, beta)?;
+
+            worst = worst.min(value);
+            beta = beta.min(value);
+
+            if worst <= alpha {
+                break;
+            }
+        }
+
+        Some(worst)
+    }
+
+    fn opponent_moves(&self, board: &BitBoard) -> Vec<Option<(usize, Direction)>> {
+        let opponent = match board.alive().find(|i| *i != self.you) {
+            Some(opponent) => opponent,
+            None => return vec![None],
+        };
+
+        let moves: Vec<_> = board
+            .valid_moves(opponent, &self.rules)
+            .into_iter()
+            .map(|dir| Some((opponent, dir)))
+            .collect();
+
+        // A trapped opponent dies whatever it does.
+        if moves.is_empty() {
+            return vec![None];
+        }
+
+        moves
+    }
+
+    // Games that are over, or out of depth. Sooner wins and later losses
+    // are preferred.
+    fn terminal_value(&self, board: &BitBoard, depth: u32) -> Option<i32> {
+        let alive = board.is_alive(self.you);
+        let opponents = board.alive().any(|i| i != self.you);
+
+        match (alive, opponents) {
+            (false, false) => Some(DRAW),
+            (false, true) => Some(LOSS - depth as i32),
+            (true, false) => Some(WIN + depth as i32),
+            (true, true) if depth == 0 => Some((self.evaluate)(board, self.you, &self.rules)),
+            (true, true) => None,
+        }
+    }
+}
+
+// Wins and losses score how far away they are from the node. The table keeps
+// them relative to the end of the game instead, so they can be reused at any
+// depth.
+fn to_table(value: i32, depth: u32) -> i32 {
+    if value > WIN / 2 {
+        value - depth as i32
+    } else if value < DRAW {
+        value + depth as i32
+    } else {
+        value
+    }
+}
+
+fn from_table(value: i32, depth: u32) -> i32 {
+    if value > WIN / 2 {
+        value + depth as i32
+    } else if value < DRAW {
+        value - depth as i32
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        domain::{Battlesnake, Board, Coord},
+        logic::{
+            fixtures::{get_board, get_snake},
+            move_validator::get_valid_moves,
+        },
+    };
+
+    fn get_bitboard(board: &Board) -> BitBoard {
+        BitBoard::new(board).unwrap()
+    }
+
+    fn get_minimax() -> Minimax {
+        Minimax::new(
+            0,
+            Rules::default(),
+            evaluate,
+            Instant::now() + Duration::from_secs(10),
+        )
+    }
+
+    #[test]
+    fn avoid_losing_head_to_head() {
+        let you = get_snake(
+            "you",
+            &[Coord::new(2, 3), Coord::new(1, 3), Coord::new(0, 3)],
+        );
+        let board = get_board(
+            7,
+            vec![
+                you.clone(),
+                get_snake(
+                    "enemy",
+                    &[
+                        Coord::new(4, 3),
+                        Coord::new(5, 3),
+                        Coord::new(6, 3),
+                        Coord::new(6, 2),
+                        Coord::new(6, 1),
+                    ],
+                ),
+            ],
+            &[],
+        );
+        let options: Vec<_> = get_valid_moves(&board, &you, &Rules::default())
+            .into_iter()
+            .collect();
+
+        let response = get_minimax().best_move(&get_bitboard(&board), &options, 2);
+
+        assert!(response.is_some());
+        assert_ne!(response, Some(Direction::Right(Coord::new(3, 3))));
+    }
+
+    #[test]
+    fn kill_trapped_opponent() {
+        // The enemy is stuck in the corner and (0, 1) is its only way out.
+        let you = get_snake(
+            "you",
+            &[
+                Coord::new(0, 2),
+                Coord::new(1, 2),
+                Coord::new(2, 2),
+                Coord::new(2, 1),
+            ],
+        );
+        let board = get_board(
+            7,
+            vec![
+                you.clone(),
+                get_snake(
+                    "enemy",
+                    &[Coord::new(0, 0), Coord::new(1, 0), Coord::new(1, 1)],
+                ),
+            ],
+            &[],
+        );
+        let options: Vec<_> = get_valid_moves(&board, &you, &Rules::default())
+            .into_iter()
+            .collect();
+
+        let response = get_minimax().best_move(&get_bitboard(&board), &options, 2);
+
+        assert_eq!(response, Some(Direction::Down(Coord::new(0, 1))));
+    }
+
+    #[test]
+    fn give_up_after_deadline() {
+        let you = get_snake("you", &[Coord::new(2, 3), Coord::new(1, 3)]);
+        let board = get_board(
+            7,
+            vec![
+                you.clone(),
+                get_snake("enemy", &[Coord::new(5, 3), Coord::new(6, 3)]),
+            ],
+            &[],
+        );
+        let minimax = Minimax::new(0, Rules::default(), evaluate, Instant::now());
+
+        let response =
+            minimax.best_move(&get_bitboard(&board), &[Direction::Up(Coord::new(2, 4))], 3);
+
+        assert_eq!(response, None);
+    }
+
+    #[test]
+    fn deepen_until_max_depth() {
+        let you = get_snake("you", &[Coord::new(2, 3), Coord::new(1, 3)]);
+        let board = get_board(
+            7,
+            vec![
+                you.clone(),
+                get_snake("enemy", &[Coord::new(5, 3), Coord::new(6, 3)]),
+            ],
+            &[],
+        );
+
+        let response = get_minimax().iterative_best_move(
+            &get_bitboard(&board),
+            &[Direction::Up(Coord::new(2, 4))],
+            2,
+        );
+
+        assert_eq!(response, Some((Direction::Up(Coord::new(2, 4)), 2)));
+    }
+
+    #[test]
+    fn remember_best_moves() {
+        let you = get_snake(
+            "you",
+            &[
+                Coord::new(0, 2),
+                Coord::new(1, 2),
+                Coord::new(2, 2),
+                Coord::new(2, 1),
+            ],
+        );
+        let board = get_board(
+            7,
+            vec![
+                you.clone(),
+                get_snake(
+                    "enemy",
+                    &[Coord::new(0, 0), Coord::new(1, 0), Coord::new(1, 1)],
+                ),
+            ],
+            &[],
+        );
+        let bitboard = get_bitboard(&board);
+        let options: Vec<_> = get_valid_moves(&board, &you, &Rules::default())
+            .into_iter()
+            .collect();
+
+        let minimax = get_minimax();
+        let response = minimax.best_move(&bitboard, &options, 3);
+        let table = minimax.into_table();
+        let entry = table.get(zobrist::hash(&bitboard)).copied();
+
+        assert_eq!(entry.map(|entry| entry.best), Some(response));
+        assert_eq!(entry.map(|entry| entry.depth), Some(3));
+
+        // Searching again with the table finds the same move.
+        let minimax = get_minimax().with_table(table);
+
+        assert_eq!(minimax.best_move(&bitboard, &options, 3), response);
+    }
+
+    #[test]
+    fn value_health_when_feeding() {
+        let you = get_snake("you", &[Coord::new(2, 3), Coord::new(1, 3)]);
+        let enemy = get_snake("enemy", &[Coord::new(5, 3), Coord::new(6, 3)]);
+        let full = get_bitboard(&get_board(7, vec![you.clone(), enemy.clone()], &[]));
+        let starving = get_bitboard(&get_board(
+            7,
+            vec![Battlesnake { health: 10, ..you }, enemy],
+            &[],
+        ));
+        let rules = Rules::default();
+
+        for hunger in [Hunger::Grow, Hunger::Idle] {
+            let evaluate = get_evaluate(hunger);
+
+            assert_eq!(evaluate(&full, 0, &rules), evaluate(&starving, 0, &rules));
+        }
+
+        let evaluate = get_evaluate(Hunger::Feed);
+
+        assert!(evaluate(&full, 0, &rules) > evaluate(&starving, 0, &rules));
+    }
+}
@@ -0,0 +1,531 @@
+use std::collections::HashMap;
+
+use rand::{seq::SliceRandom, Rng};
+
+use crate::domain::{Battlesnake, Board, Coord, Direction, RulesetSettings};
+
+use super::rules::Rules;
+
+pub const MAX_HEALTH: i32 = 100;
+
+// Advance the board one turn under the standard rules.
+// See https://docs.battlesnake.com/guides/game/rules
+//
+// `moves` maps every snake id to its next move. A snake without a move keeps
+// going in the direction it was already heading, as the engine does.
+pub fn simulate(board: &Board, moves: &HashMap<String, Direction>, rules: &Rules) -> Board {
+    let mut next = board.clone();
+
+    for snake in next.snakes.iter_mut() {
+        let next_head = match moves.get(&snake.id) {
+            Some(dir) => *dir.get_coord(),
+            None => default_move(board, snake, rules),
+        };
+
+        move_snake(snake, rules.topology.normalize(board, next_head));
+        snake.health -= 1;
+    }
+
+    damage_hazards(&mut next, rules);
+    let fed = feed_snakes(&mut next);
+
+    if !rules.tails_vacate() {
+        grow_snakes(&mut next, &fed);
+    }
+
+    eliminate_snakes(&mut next, rules);
+
+    next
+}
+
+fn default_move(board: &Board, snake: &Battlesnake, rules: &Rules) -> Coord {
+    let head = snake.body[0];
+    let neighbours = rules.topology.neighbours(board, &head);
+
+    // Repeat the move that brought the neck to the head, or go up.
+    let index = snake
+        .body
+        .get(1)
+        .and_then(|neck| {
+            rules
+                .topology
+                .neighbours(board, neck)
+                .iter()
+                .position(|dir| *dir.get_coord() == head)
+        })
+        .unwrap_or(0);
+
+    *neighbours[index].get_coord()
+}
+
+fn move_snake(snake: &mut Battlesnake, next_head: Coord) {
+    snake.body.insert(0, next_head);
+    snake.body.pop();
+    snake.head = next_head;
+}
+
+// Snakes that eat on a hazard don't take its damage.
+fn damage_hazards(board: &mut Board, rules: &Rules) {
+    let costs: Vec<i32> = board
+        .snakes
+        .iter()
+        .map(|snake| {
+            if board.food.contains(&snake.head) {
+                0
+            } else {
+                rules.hazard_cost(board, &snake.head)
+            }
+        })
+        .collect();
+
+    for (snake, cost) in board.snakes.iter_mut().zip(costs) {
+        snake.health -= cost;
+    }
+}
+
+// Returns the ids of the snakes that ate.
+fn feed_snakes(board: &mut Board) -> Vec<String> {
+    let mut eaten: Vec<Coord> = vec![];
+    let mut fed: Vec<String> = vec![];
+
+    for snake in board.snakes.iter_mut() {
+        if board.food.contains(&snake.head) {
+            grow(snake);
+            eaten.push(snake.head);
+            fed.push(snake.id.clone());
+        }
+    }
+
+    board.food.retain(|food| !eaten.contains(food));
+
+    fed
+}
+
+// Constrictor snakes grow every turn and never go hungry. Eating doesn't
+// make them grow any more than that.
+fn grow_snakes(board: &mut Board, fed: &[String]) {
+    for snake in board.snakes.iter_mut() {
+        if !fed.contains(&snake.id) {
+            grow(snake);
+        }
+    }
+}
+
+fn grow(snake: &mut Battlesnake) {
+    let tail = snake.body[snake.body.len() - 1];
+
+    snake.health = MAX_HEALTH;
+    snake.body.push(tail);
+    snake.length = snake.body.len() as i32;
+}
+
+fn eliminate_snakes(board: &mut Board, rules: &Rules) {
+    // Starvation and walls are resolved first, collisions are only checked
+    // against the snakes that survived them.
+    let eliminated: Vec<String> = board
+        .snakes
+        .iter()
+        .filter(|snake| snake.health <= 0 || !rules.topology.contains(board, &snake.head))
+        .map(|snake| snake.id.clone())
+        .collect();
+
+    board.snakes.retain(|snake| !eliminated.contains(&snake.id));
+
+    let eliminated: Vec<String> = board
+        .snakes
+        .iter()
+        .filter(|snake| {
+            is_self_collision(snake)
+                || is_body_collision(snake, &board.snakes)
+                || is_head_collision_lost(snake, &board.snakes)
+        })
+        .map(|snake| snake.id.clone())
+        .collect();
+
+    board.snakes.retain(|snake| !eliminated.contains(&snake.id));
+}
+
+fn is_self_collision(snake: &Battlesnake) -> bool {
+    snake.body[1..].contains(&snake.head)
+}
+
+fn is_body_collision(snake: &Battlesnake, snakes: &[Battlesnake]) -> bool {
+    snakes
+        .iter()
+        .filter(|other| other.id != snake.id)
+        .any(|other| other.body[1..].contains(&snake.head))
+}
+
+// Head to head collisions eliminate the shorter snake, or both of them
+// when they have the same length.
+fn is_head_collision_lost(snake: &Battlesnake, snakes: &[Battlesnake]) -> bool {
+    snakes
+        .iter()
+        .filter(|other| other.id != snake.id)
+        .any(|other| other.head == snake.head && other.body.len() >= snake.body.len())
+}
+
+// Tops the food up to `minimum_food`, otherwise adds one more with a
+// `food_spawn_chance` percent chance. Food only goes on free cells.
+pub fn spawn_food(board: &mut Board, settings: &RulesetSettings, rng: &mut impl Rng) {
+    let missing = settings.minimum_food - board.food.len() as i32;
+
+    let count = if missing > 0 {
+        missing as usize
+    } else if rng.gen_range(0..100) < settings.food_spawn_chance {
+        1
+    } else {
+        return;
+    };
+
+    let free: Vec<Coord> = (0..board.height as i32)
+        .flat_map(|y| (0..board.width as i32).map(move |x| Coord::new(x, y)))
+        .filter(|coord| {
+            !board.food.contains(coord)
+                && board.snakes.iter().all(|snake| !snake.body.contains(coord))
+        })
+        .collect();
+
+    board.food.extend(free.choose_multiple(rng, count).copied());
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::domain::Ruleset;
+    use crate::logic::fixtures::{get_board, get_snake_with_health};
+
+    fn get_moves(moves: &[(&str, Direction)]) -> HashMap<String, Direction> {
+        moves
+            .iter()
+            .map(|(id, dir)| (String::from(*id), *dir))
+            .collect()
+    }
+
+    #[test]
+    fn move_and_shrink_tail() {
+        let board = get_board(
+            5,
+            vec![get_snake_with_health(
+                "you",
+                &[Coord::new(1, 1), Coord::new(1, 0), Coord::new(0, 0)],
+                50,
+            )],
+            &[],
+        );
+
+        let next = simulate(
+            &board,
+            &get_moves(&[("you", Direction::Up(Coord::new(1, 2)))]),
+            &Rules::default(),
+        );
+        let you = &next.snakes[0];
+
+        assert_eq!(
+            you.body,
+            vec![Coord::new(1, 2), Coord::new(1, 1), Coord::new(1, 0)]
+        );
+        assert_eq!(you.head, Coord::new(1, 2));
+        assert_eq!(you.health, 49);
+    }
+
+    #[test]
+    fn eat_and_grow() {
+        let board = get_board(
+            5,
+            vec![get_snake_with_health(
+                "you",
+                &[Coord::new(1, 1), Coord::new(1, 0), Coord::new(0, 0)],
+                50,
+            )],
+            &[Coord::new(2, 1), Coord::new(4, 4)],
+        );
+
+        let next = simulate(
+            &board,
+            &get_moves(&[("you", Direction::Right(Coord::new(2, 1)))]),
+            &Rules::default(),
+        );
+        let you = &next.snakes[0];
+
+        assert_eq!(you.health, 100);
+        assert_eq!(you.length, 4);
+        assert_eq!(you.body[2], you.body[3]);
+        assert_eq!(next.food, vec![Coord::new(4, 4)]);
+    }
+
+    #[test]
+    fn starve_and_hit_walls() {
+        let board = get_board(
+            5,
+            vec![
+                get_snake_with_health("hungry", &[Coord::new(2, 2), Coord::new(2, 1)], 1),
+                get_snake_with_health("lost", &[Coord::new(0, 4), Coord::new(0, 3)], 50),
+            ],
+            &[],
+        );
+
+        let next = simulate(
+            &board,
+            &get_moves(&[
+                ("hungry", Direction::Up(Coord::new(2, 3))),
+                ("lost", Direction::Up(Coord::new(0, 5))),
+            ]),
+            &Rules::default(),
+        );
+
+        assert!(next.snakes.is_empty());
+    }
+
+    #[test]
+    fn body_collisions() {
+        let enemy = get_snake_with_health(
+            "enemy",
+            &[Coord::new(2, 2), Coord::new(2, 1), Coord::new(2, 0)],
+            50,
+        );
+
+        // Moving into the tail the enemy just left behind is safe.
+        let board = get_board(
+            5,
+            vec![
+                get_snake_with_health("you", &[Coord::new(1, 0), Coord::new(0, 0)], 50),
+                enemy.clone(),
+            ],
+            &[],
+        );
+
+        let next = simulate(
+            &board,
+            &get_moves(&[
+                ("you", Direction::Right(Coord::new(2, 0))),
+                ("enemy", Direction::Up(Coord::new(2, 3))),
+            ]),
+            &Rules::default(),
+        );
+
+        assert_eq!(next.snakes.len(), 2);
+
+        // Moving into its body is not.
+        let board = get_board(
+            5,
+            vec![
+                get_snake_with_health("you", &[Coord::new(1, 1), Coord::new(0, 1)], 50),
+                enemy,
+            ],
+            &[],
+        );
+
+        let next = simulate(
+            &board,
+            &get_moves(&[
+                ("you", Direction::Right(Coord::new(2, 1))),
+                ("enemy", Direction::Up(Coord::new(2, 3))),
+            ]),
+            &Rules::default(),
+        );
+
+        assert_eq!(next.snakes.len(), 1);
+        assert_eq!(next.snakes[0].id, "enemy");
+    }
+
+    #[test]
+    fn head_to_head() {
+        let board = get_board(
+            5,
+            vec![
+                get_snake_with_health(
+                    "long",
+                    &[Coord::new(1, 2), Coord::new(0, 2), Coord::new(0, 1)],
+                    50,
+                ),
+                get_snake_with_health("short", &[Coord::new(3, 2), Coord::new(4, 2)], 50),
+            ],
+            &[],
+        );
+
+        let next = simulate(
+            &board,
+            &get_moves(&[
+                ("long", Direction::Right(Coord::new(2, 2))),
+                ("short", Direction::Left(Coord::new(2, 2))),
+            ]),
+            &Rules::default(),
+        );
+
+        assert_eq!(next.snakes.len(), 1);
+        assert_eq!(next.snakes[0].id, "long");
+
+        let board = get_board(
+            5,
+            vec![
+                get_snake_with_health("left", &[Coord::new(1, 2), Coord::new(0, 2)], 50),
+                get_snake_with_health("right", &[Coord::new(3, 2), Coord::new(4, 2)], 50),
+            ],
+            &[],
+        );
+
+        let next = simulate(
+            &board,
+            &get_moves(&[
+                ("left", Direction::Right(Coord::new(2, 2))),
+                ("right", Direction::Left(Coord::new(2, 2))),
+            ]),
+            &Rules::default(),
+        );
+
+        assert!(next.snakes.is_empty());
+    }
+
+    #[test]
+    fn hazard_damage() {
+        let board = Board {
+            hazards: vec![Coord::new(2, 3), Coord::new(2, 3), Coord::new(3, 2)],
+            ..get_board(
+                5,
+                vec![
+                    get_snake_with_health("stacked", &[Coord::new(2, 2), Coord::new(2, 1)], 50),
+                    get_snake_with_health("fed", &[Coord::new(4, 2), Coord::new(4, 1)], 50),
+                ],
+                &[Coord::new(3, 2)],
+            )
+        };
+
+        let next = simulate(
+            &board,
+            &get_moves(&[
+                ("stacked", Direction::Up(Coord::new(2, 3))),
+                ("fed", Direction::Left(Coord::new(3, 2))),
+            ]),
+            &Rules::default(),
+        );
+
+        assert_eq!(next.snakes[0].health, 50 - 1 - 28);
+        assert_eq!(next.snakes[1].health, 100);
+    }
+
+    #[test]
+    fn keep_heading_without_move() {
+        let board = get_board(
+            5,
+            vec![get_snake_with_health(
+                "you",
+                &[Coord::new(2, 2), Coord::new(1, 2)],
+                50,
+            )],
+            &[],
+        );
+
+        let next = simulate(&board, &HashMap::new(), &Rules::default());
+
+        assert_eq!(next.snakes[0].head, Coord::new(3, 2));
+    }
+    #[test]
+    fn wrap_around_edges() {
+        let board = get_board(
+            5,
+            vec![get_snake_with_health(
+                "you",
+                &[Coord::new(4, 2), Coord::new(3, 2)],
+                50,
+            )],
+            &[],
+        );
+        let rules = Rules::new(&Ruleset::new("wrapped"));
+
+        let next = simulate(
+            &board,
+            &get_moves(&[("you", Direction::Right(Coord::new(0, 2)))]),
+            &rules,
+        );
+
+        assert_eq!(next.snakes[0].head, Coord::new(0, 2));
+
+        let next = simulate(&next, &HashMap::new(), &rules);
+
+        assert_eq!(next.snakes[0].head, Coord::new(1, 2));
+    }
+
+    #[test]
+    fn constrictor_growth() {
+        let board = get_board(
+            5,
+            vec![get_snake_with_health(
+                "you",
+                &[Coord::new(2, 2), Coord::new(1, 2)],
+                50,
+            )],
+            &[],
+        );
+
+        let next = simulate(
+            &board,
+            &get_moves(&[("you", Direction::Up(Coord::new(2, 3)))]),
+            &Rules::new(&Ruleset::new("constrictor")),
+        );
+
+        assert_eq!(
+            next.snakes[0].body,
+            vec![Coord::new(2, 3), Coord::new(2, 2), Coord::new(2, 2)]
+        );
+        assert_eq!(next.snakes[0].health, 100);
+    }
+
+    #[test]
+    fn constrictor_eat_grows_once() {
+        let board = get_board(
+            5,
+            vec![get_snake_with_health(
+                "you",
+                &[Coord::new(2, 2), Coord::new(1, 2)],
+                50,
+            )],
+            &[Coord::new(2, 3)],
+        );
+
+        let next = simulate(
+            &board,
+            &get_moves(&[("you", Direction::Up(Coord::new(2, 3)))]),
+            &Rules::new(&Ruleset::new("constrictor")),
+        );
+
+        assert_eq!(
+            next.snakes[0].body,
+            vec![Coord::new(2, 3), Coord::new(2, 2), Coord::new(2, 2)]
+        );
+        assert_eq!(next.snakes[0].length, 3);
+        assert!(next.food.is_empty());
+    }
+
+    #[test]
+    fn spawn_food_on_free_cells() {
+        let mut board = get_board(
+            5,
+            vec![get_snake_with_health(
+                "you",
+                &[Coord::new(1, 1), Coord::new(1, 0), Coord::new(0, 0)],
+                50,
+            )],
+            &[],
+        );
+        let settings = RulesetSettings {
+            minimum_food: 22,
+            food_spawn_chance: 100,
+            ..RulesetSettings::default()
+        };
+        let mut rng = StdRng::seed_from_u64(1);
+
+        spawn_food(&mut board, &settings, &mut rng);
+        assert_eq!(board.food.len(), 22);
+        assert!(board
+            .food
+            .iter()
+            .all(|food| !board.snakes[0].body.contains(food)));
+
+        // Board full, nowhere left to put more.
+        spawn_food(&mut board, &settings, &mut rng);
+        assert_eq!(board.food.len(), 22);
+    }
+}
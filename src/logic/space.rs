@@ -0,0 +1,205 @@
+use std::collections::VecDeque;
+
+use crate::domain::{Battlesnake, Board, Coord, Direction};
+
+use super::rules::Rules;
+
+// Number of cells we can reach once our head is on `start`. Body segments
+// count as free cells if they will have moved away by the time we get there.
+pub fn reachable_area(board: &Board, you: &Battlesnake, rules: &Rules, start: &Coord) -> usize {
+    let free_at = get_free_turns(board, rules);
+    let mut visited = vec![vec![false; board.width as usize]; board.height as usize];
+
+    // Our head is on `start` after the first turn.
+    let mut queue: VecDeque<(Coord, usize)> = VecDeque::from([(*start, 1)]);
+    visited[start.y as usize][start.x as usize] = true;
+
+    let mut area = 0;
+
+    while let Some((coord, turn)) = queue.pop_front() {
+        area += 1;
+
+        for dir in rules.topology.neighbours(board, &coord) {
+            let next = *dir.get_coord();
+
+            if !rules.topology.contains(board, &next) {
+                continue;
+            }
+
+            let (x, y) = (next.x as usize, next.y as usize);
+
+            if visited[y][x]
+                || free_at[y][x] > turn + 1
+                || rules.hazard_cost(board, &next) >= you.health
+            {
+                continue;
+            }
+
+            visited[y][x] = true;
+            queue.push_back((next, turn + 1));
+        }
+    }
+
+    area
+}
+
+// Reject the moves leading to pockets smaller than our body. If every move
+// does, keep the roomiest ones.
+pub fn avoid_traps(
+    options: &[Direction],
+    board: &Board,
+    you: &Battlesnake,
+    rules: &Rules,
+) -> Vec<Direction> {
+    let areas: Vec<(Direction, usize)> = options
+        .iter()
+        .map(|opt| (*opt, reachable_area(board, you, rules, opt.get_coord())))
+        .collect();
+
+    let roomy: Vec<Direction> = areas
+        .iter()
+        .filter(|(_, area)| *area >= you.length as usize)
+        .map(|(opt, _)| *opt)
+        .collect();
+
+    if !roomy.is_empty() {
+        return roomy;
+    }
+
+    let largest = areas.iter().map(|(_, area)| *area).max();
+
+    areas
+        .into_iter()
+        .filter(|(_, area)| Some(*area) == largest)
+        .map(|(opt, _)| opt)
+        .collect()
+}
+
+// The move leaving us the most room, if any.
+pub fn get_roomiest_move(
+    options: &[Direction],
+    board: &Board,
+    you: &Battlesnake,
+    rules: &Rules,
+) -> Option<Direction> {
+    options
+        .iter()
+        .rev()
+        .max_by_key(|opt| reachable_area(board, you, rules, opt.get_coord()))
+        .copied()
+}
+
+// The turn after which each cell is free to step on. The segment at index `i`
+// is gone after `length - i` moves, unless tails never move.
+pub(super) fn get_free_turns(board: &Board, rules: &Rules) -> Vec<Vec<usize>> {
+    let mut free_at = vec![vec![0; board.width as usize]; board.height as usize];
+
+    for snake in board.snakes.iter() {
+        let length = snake.body.len();
+
+        for (i, segment) in snake.body.iter().enumerate() {
+            if !rules.topology.contains(board, segment) {
+                continue;
+            }
+
+            let turns = if rules.tails_vacate() {
+                length - i
+            } else {
+                usize::MAX
+            };
+            let cell = &mut free_at[segment.y as usize][segment.x as usize];
+
+            *cell = (*cell).max(turns);
+        }
+    }
+
+    free_at
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Ruleset;
+    use crate::logic::fixtures::{get_board, get_snake};
+
+    // A wall of body closing a one column pocket on the left, that won't
+    // move away before we fill it.
+    fn get_pocket_snake() -> Battlesnake {
+        get_snake(
+            "test",
+            &[
+                Coord::new(1, 0),
+                Coord::new(1, 1),
+                Coord::new(1, 2),
+                Coord::new(1, 3),
+                Coord::new(1, 4),
+                Coord::new(2, 4),
+                Coord::new(3, 4),
+                Coord::new(4, 4),
+                Coord::new(4, 3),
+                Coord::new(4, 2),
+                Coord::new(4, 1),
+            ],
+        )
+    }
+
+    #[test]
+    fn count_reachable_area() {
+        let you = get_pocket_snake();
+        let board = get_board(5, vec![you.clone()], &[]);
+        let rules = Rules::default();
+
+        assert_eq!(reachable_area(&board, &you, &rules, &Coord::new(0, 0)), 5);
+        assert_eq!(reachable_area(&board, &you, &rules, &Coord::new(2, 0)), 25);
+    }
+
+    #[test]
+    fn count_vacated_tails() {
+        let you = get_snake(
+            "test",
+            &[Coord::new(0, 0), Coord::new(0, 1), Coord::new(1, 1)],
+        );
+        let board = get_board(5, vec![you.clone()], &[]);
+
+        // The tail is gone when we get next to it.
+        let area = reachable_area(&board, &you, &Rules::default(), &Coord::new(1, 0));
+        assert_eq!(area, 25);
+
+        let rules = Rules::new(&Ruleset::new("constrictor"));
+        let area = reachable_area(&board, &you, &rules, &Coord::new(1, 0));
+        assert_eq!(area, 22);
+    }
+
+    #[test]
+    fn avoid_small_pockets() {
+        let you = get_pocket_snake();
+        let board = get_board(5, vec![you.clone()], &[]);
+
+        let options = vec![
+            Direction::Left(Coord::new(0, 0)),
+            Direction::Right(Coord::new(2, 0)),
+        ];
+
+        let response = avoid_traps(&options, &board, &you, &Rules::default());
+
+        assert_eq!(response, vec![Direction::Right(Coord::new(2, 0))]);
+    }
+
+    #[test]
+    fn pick_roomiest_move() {
+        let you = get_pocket_snake();
+        let board = get_board(5, vec![you.clone()], &[]);
+        let rules = Rules::default();
+
+        let options = vec![
+            Direction::Left(Coord::new(0, 0)),
+            Direction::Right(Coord::new(2, 0)),
+        ];
+
+        assert_eq!(
+            get_roomiest_move(&options, &board, &you, &rules),
+            Some(Direction::Right(Coord::new(2, 0)))
+        );
+        assert_eq!(get_roomiest_move(&[], &board, &you, &rules), None);
+    }
+}
@@ -0,0 +1,474 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    env,
+    hash::{Hash, Hasher},
+    time::{Duration, Instant},
+};
+
+use log::{info, warn};
+
+use crate::{
+    domain::{Battlesnake, Board, Direction, GameState},
+    session::Session,
+};
+
+use super::{
+    bitboard::BitBoard,
+    deadline, fallback,
+    food_finder::get_next_step,
+    hunger::{self, Hunger},
+    mcts::Mcts,
+    move_refinator, move_validator,
+    rules::Rules,
+    search::{self, Minimax},
+    space, tail_chaser, territory,
+};
+
+// Deepest we try to search one versus one endgames.
+const MAX_SEARCH_DEPTH: u32 = 32;
+
+pub const DEFAULT_STRATEGY: &str = "minimax";
+
+// Far enough away that only the budget stops a search.
+const UNLIMITED: Duration = Duration::from_secs(60 * 60);
+
+// Fixed amount of work per move instead of thinking until the deadline, so
+// offline games run fast and play the same on any machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Budget {
+    // Deepest minimax goes.
+    pub depth: u32,
+    // Playouts of the tree search.
+    pub iterations: u32,
+    // Seeds the tree search, along with the turn.
+    pub seed: u64,
+}
+
+// A way of playing. The same strategy plays every game at once, anything it
+// needs to remember about one of them goes in its session.
+pub trait Strategy: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn start(&self, _state: &GameState, _session: &mut Session) {}
+
+    fn choose_move(&self, state: &GameState, session: &mut Session) -> Direction;
+
+    // Said along with the chosen move, to debug live games from the board
+    // viewer.
+    fn shout(&self, _state: &GameState, session: &Session) -> Option<String> {
+        session.plan.clone()
+    }
+
+    fn end(&self, _state: &GameState, _session: &mut Session) {}
+}
+
+pub fn get_strategy(name: &str) -> Option<Box<dyn Strategy>> {
+    get_strategy_with(name, None)
+}
+
+// The same strategies, held to the budget instead of the deadline.
+pub fn get_budgeted_strategy(name: &str, budget: Budget) -> Option<Box<dyn Strategy>> {
+    get_strategy_with(name, Some(budget))
+}
+
+fn get_strategy_with(name: &str, budget: Option<Budget>) -> Option<Box<dyn Strategy>> {
+    match name {
+        "heuristic" => Some(Box::new(HeuristicStrategy)),
+        "minimax" => Some(Box::new(MinimaxStrategy { budget })),
+        "mcts" => Some(Box::new(MctsStrategy { budget })),
+        _ => None,
+    }
+}
+
+// The strategies we play with. With more than one, each game gets one of
+// them, always the same for the same game, to compare them (A/B testing).
+pub struct Strategies(Vec<Box<dyn Strategy>>);
+
+impl Strategies {
+    // Comma separated strategy names, unknown ones are skipped.
+    pub fn new(names: &str) -> Self {
+        let mut strategies: Vec<_> = names
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .filter_map(|name| {
+                let strategy = get_strategy(name);
+
+                if strategy.is_none() {
+                    warn!("Unknown strategy {}", name);
+                }
+
+                strategy
+            })
+            .collect();
+
+        if strategies.is_empty() {
+            strategies.extend(get_strategy(DEFAULT_STRATEGY));
+        }
+
+        Strategies(strategies)
+    }
+
+    // From the `BATTLESNAKE_STRATEGY` environment variable, or the
+    // `BATTLESNAKE_ENGINE` one it replaced, for older deployments.
+    pub fn from_env() -> Self {
+        let names = env::var("BATTLESNAKE_STRATEGY")
+            .or_else(|_| env::var("BATTLESNAKE_ENGINE"))
+            .unwrap_or_default();
+
+        Strategies::new(&names)
+    }
+
+    pub fn for_game(&self, game_id: &str) -> &dyn Strategy {
+        let mut hasher = DefaultHasher::new();
+        game_id.hash(&mut hasher);
+
+        self.0[hasher.finish() as usize % self.0.len()].as_ref()
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.0.iter().map(|strategy| strategy.name()).collect()
+    }
+}
+
+// Rules of thumb only: take safe moves, prefer the recommended and refined
+// ones, then the cheapest hazards, the most territory and the way to food.
+pub struct HeuristicStrategy;
+
+impl Strategy for HeuristicStrategy {
+    fn name(&self) -> &str {
+        "heuristic"
+    }
+
+    fn choose_move(&self, state: &GameState, _session: &mut Session) -> Direction {
+        let rules = Rules::new(state.game.ruleset());
+
+        match get_safe_moves(state, &rules) {
+            Some(safe_moves) => {
+                get_heuristic_move(state, &rules, safe_moves, get_hunger(state, &rules))
+            }
+            None => get_default_move(state, &rules),
+        }
+    }
+}
+
+// Looks ahead with alpha-beta in one versus one games, and follows the rules
+// of thumb otherwise or when the search runs out of time.
+pub struct MinimaxStrategy {
+    budget: Option<Budget>,
+}
+
+impl Strategy for MinimaxStrategy {
+    fn name(&self) -> &str {
+        "minimax"
+    }
+
+    fn choose_move(&self, state: &GameState, session: &mut Session) -> Direction {
+        let deadline = get_deadline(state, session, self.budget);
+        let depth = self.budget.map_or(MAX_SEARCH_DEPTH, |budget| {
+            budget.depth.min(MAX_SEARCH_DEPTH)
+        });
+        let rules = Rules::new(state.game.ruleset());
+
+        let safe_moves = match get_safe_moves(state, &rules) {
+            Some(safe_moves) => safe_moves,
+            None => return get_default_move(state, &rules),
+        };
+        let hunger = get_hunger(state, &rules);
+
+        if let Some((bitboard, index)) =
+            get_bitboard(state).filter(|_| state.board.snakes.len() == 2)
+        {
+            let minimax = Minimax::new(index, rules, search::get_evaluate(hunger), deadline)
+                .with_table(session.take_table(hunger));
+            let best = minimax.iterative_best_move(&bitboard, &safe_moves, depth);
+
+            session.table = minimax.into_table();
+
+            if let Some((direction, depth)) = best {
+                info!("MINIMAX {}: depth {}", direction.as_str(), depth);
+                session.plan = Some(format!("minimax depth {}, {}", depth, hunger.as_str()));
+                return direction;
+            }
+        }
+
+        get_heuristic_move(state, &rules, safe_moves, hunger)
+    }
+}
+
+// Monte Carlo tree search against every other snake, falling back to the
+// rules of thumb if it couldn't run.
+pub struct MctsStrategy {
+    budget: Option<Budget>,
+}
+
+impl Strategy for MctsStrategy {
+    fn name(&self) -> &str {
+        "mcts"
+    }
+
+    fn choose_move(&self, state: &GameState, session: &mut Session) -> Direction {
+        let deadline = get_deadline(state, session, self.budget);
+        let (iterations, seed) = match self.budget {
+            Some(budget) => (
+                budget.iterations,
+                budget.seed.wrapping_add(state.turn as u64),
+            ),
+            None => (u32::MAX, rand::random()),
+        };
+        let rules = Rules::new(state.game.ruleset());
+
+        let safe_moves = match get_safe_moves(state, &rules) {
+            Some(safe_moves) => safe_moves,
+            None => return get_default_move(state, &rules),
+        };
+        let hunger = get_hunger(state, &rules);
+
+        if let Some((bitboard, index)) =
+            get_bitboard(state).filter(|_| state.board.snakes.len() > 1)
+        {
+            let mut mcts = Mcts::new(index, rules, deadline, seed).with_hunger(hunger);
+            let stats = mcts.search(&bitboard, &safe_moves, iterations);
+
+            for stat in stats.iter() {
+                info!(
+                    "MCTS {}: {} visits, {:.2} win rate",
+                    stat.direction.as_str(),
+                    stat.visits,
+                    stat.win_rate
+                );
+            }
+
+            if let Some(best) = stats.first().filter(|stat| stat.visits > 0) {
+                session.plan = Some(format!(
+                    "mcts {} visits, {:.0}% win rate, {}",
+                    best.visits,
+                    100.0 * best.win_rate,
+                    hunger.as_str()
+                ));
+                return best.direction;
+            }
+        }
+
+        get_heuristic_move(state, &rules, safe_moves, hunger)
+    }
+}
+
+// Counted from when the request arrived, leaving out the latency we measured.
+fn get_deadline(state: &GameState, session: &Session, budget: Option<Budget>) -> Instant {
+    match budget {
+        Some(_) => Instant::now() + UNLIMITED,
+        None => deadline::get_deadline(
+            &state.game,
+            session.last_seen,
+            deadline::get_margin(&state.you, &session.timings),
+        ),
+    }
+}
+
+// Moves that don't lose right away, keeping out of pockets smaller than
+// ourselves when possible. None when every move loses.
+fn get_safe_moves(state: &GameState, rules: &Rules) -> Option<Vec<Direction>> {
+    let valid_moves = move_validator::get_valid_moves(&state.board, &state.you, rules);
+
+    if valid_moves.is_empty() {
+        return None;
+    }
+
+    // Keep the order stable so budgeted games are reproducible.
+    let safe_moves: Vec<Direction> = rules
+        .topology
+        .neighbours(&state.board, &state.you.head)
+        .into_iter()
+        .filter(|dir| valid_moves.contains(dir))
+        .collect();
+
+    Some(space::avoid_traps(
+        &safe_moves,
+        &state.board,
+        &state.you,
+        rules,
+    ))
+}
+
+// How badly we want food, which every strategy follows.
+fn get_hunger(state: &GameState, rules: &Rules) -> Hunger {
+    let settings = &state.game.ruleset().settings;
+    let hunger = hunger::get_hunger(&state.board, &state.you, rules, settings);
+
+    info!("HUNGER {}: {}", state.turn, hunger.as_str());
+    hunger
+}
+
+fn get_heuristic_move(
+    state: &GameState,
+    rules: &Rules,
+    safe_moves: Vec<Direction>,
+    hunger: Hunger,
+) -> Direction {
+    let (board, you) = (&state.board, &state.you);
+
+    if let Some(direction) = move_refinator::recommend_move(&safe_moves, you, board, rules) {
+        return *direction;
+    }
+
+    let refined_moves = move_refinator::refined_movements(&safe_moves, board, you, rules);
+
+    let options = if !refined_moves.is_empty() {
+        refined_moves
+    } else {
+        safe_moves
+    };
+    let options = move_refinator::avoid_hazards(&options, board, rules);
+    let options = territory::prefer_territory(&options, board, you, rules);
+
+    get_next_move(board, you, rules, &options, hunger)
+        .unwrap_or_else(|| get_default_move(state, rules))
+}
+
+// Food if it's worth going for. Otherwise stall behind our tail, or at least
+// make room.
+fn get_next_move(
+    board: &Board,
+    you: &Battlesnake,
+    rules: &Rules,
+    options: &[Direction],
+    hunger: Hunger,
+) -> Option<Direction> {
+    get_next_step(board, you, rules, options, hunger)
+        .or_else(|| tail_chaser::chase_tail(board, you, rules, options))
+        .or_else(|| space::get_roomiest_move(options, board, you, rules))
+}
+
+// Nothing is safe, so take the move we are most likely to survive.
+fn get_default_move(state: &GameState, rules: &Rules) -> Direction {
+    fallback::get_last_resort(&state.board, &state.you, rules)
+}
+
+// The searches run on a compact copy of the board. Boards too big for it
+// are left to the rules of thumb.
+fn get_bitboard(state: &GameState) -> Option<(BitBoard, usize)> {
+    let bitboard = BitBoard::new(&state.board)?;
+    let index = bitboard.snake_index(&state.you.id)?;
+
+    Some((bitboard, index))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Battlesnake, Board, Coord, Game, Ruleset};
+
+    fn get_state() -> GameState {
+        let you = Battlesnake {
+            id: String::from("you"),
+            name: String::from("you"),
+            health: 100,
+            length: 3,
+            body: vec![Coord::new(0, 2), Coord::new(0, 1), Coord::new(0, 0)],
+            head: Coord::new(0, 2),
+            latency: String::from("0"),
+            shout: None,
+        };
+
+        GameState {
+            game: Game::new("game", Ruleset::new("standard"), 500),
+            turn: 2,
+            board: Board {
+                height: 3,
+                width: 2,
+                food: vec![],
+                snakes: vec![you.clone()],
+                hazards: vec![],
+            },
+            you,
+        }
+    }
+
+    #[test]
+    fn pick_strategies_by_name() {
+        let strategies = Strategies::new("mcts, nope,heuristic");
+
+        assert_eq!(strategies.names(), vec!["mcts", "heuristic"]);
+        assert_eq!(Strategies::new("").names(), vec![DEFAULT_STRATEGY]);
+    }
+
+    #[test]
+    fn same_strategy_for_the_whole_game() {
+        let strategies = Strategies::new("heuristic,minimax,mcts");
+        let name = strategies.for_game("game").name();
+
+        assert!((0..10).all(|_| strategies.for_game("game").name() == name));
+        assert!((0..100)
+            .map(|i| strategies.for_game(&format!("game-{}", i)).name())
+            .any(|other| other != name));
+    }
+
+    #[test]
+    fn every_strategy_moves_safely() {
+        // Only right is safe in the top left corner.
+        for name in ["heuristic", "minimax", "mcts"] {
+            let strategy = get_strategy(name).unwrap();
+            let mut session = Session::new(Instant::now());
+
+            assert_eq!(
+                strategy.choose_move(&get_state(), &mut session),
+                Direction::Right(Coord::new(1, 2))
+            );
+        }
+    }
+
+    #[test]
+    fn chase_tail_without_safe_food() {
+        // A longer rival gets to the food as soon as we do. Left is as roomy
+        // as up, but closes us in a pocket away from our tail.
+        let you = Battlesnake {
+            id: String::from("you"),
+            name: String::from("you"),
+            health: 100,
+            length: 3,
+            body: vec![Coord::new(2, 0), Coord::new(3, 0), Coord::new(4, 0)],
+            head: Coord::new(2, 0),
+            latency: String::from("0"),
+            shout: None,
+        };
+        let rival = Battlesnake {
+            id: String::from("rival"),
+            name: String::from("rival"),
+            length: 4,
+            body: vec![
+                Coord::new(0, 2),
+                Coord::new(0, 1),
+                Coord::new(1, 1),
+                Coord::new(1, 2),
+            ],
+            head: Coord::new(0, 2),
+            ..you.clone()
+        };
+        let board = Board {
+            height: 3,
+            width: 5,
+            food: vec![Coord::new(2, 2)],
+            snakes: vec![you.clone(), rival],
+            hazards: vec![],
+        };
+        let rules = Rules::default();
+        let options = [
+            Direction::Left(Coord::new(1, 0)),
+            Direction::Up(Coord::new(2, 1)),
+        ];
+
+        assert_eq!(
+            get_next_step(&board, &you, &rules, &options, Hunger::Grow),
+            None
+        );
+        assert_eq!(
+            get_next_move(&board, &you, &rules, &options, Hunger::Grow),
+            Some(Direction::Up(Coord::new(2, 1)))
+        );
+        assert_eq!(
+            space::get_roomiest_move(&options, &board, &you, &rules),
+            Some(Direction::Left(Coord::new(1, 0)))
+        );
+    }
+}
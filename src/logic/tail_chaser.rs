@@ -0,0 +1,265 @@
+use std::collections::VecDeque;
+
+use crate::domain::{Battlesnake, Board, Coord, Direction};
+
+use super::{food_finder::Step, rules::Rules, space::get_roomiest_move};
+
+// Turns we make sure we can keep following our tail for.
+const LOOKAHEAD: usize = 4;
+
+// Stalls by following our own tail, which keeps moving out of the way, while
+// the board changes around us. Only moves after which we can keep doing so
+// for a few turns are taken, the roomiest of them. None if there are none or
+// tails never move.
+pub fn chase_tail(
+    board: &Board,
+    you: &Battlesnake,
+    rules: &Rules,
+    options: &[Direction],
+) -> Option<Direction> {
+    if !rules.tails_vacate() {
+        return None;
+    }
+
+    let stalling: Vec<Direction> = options
+        .iter()
+        .filter(|dir| can_stall(board, you, rules, **dir))
+        .copied()
+        .collect();
+
+    get_roomiest_move(&stalling, board, you, rules)
+}
+
+// Takes the move and then walks towards the tail, checking it's still
+// reachable every turn.
+fn can_stall(board: &Board, you: &Battlesnake, rules: &Rules, first: Direction) -> bool {
+    let mut step = Step::first(first, you, board, rules);
+
+    for _ in 0..LOOKAHEAD {
+        if step.state.health <= 0 {
+            return false;
+        }
+
+        let next = get_tail_move(board, you, rules, &step.state.body)
+            .or_else(|| get_waiting_move(board, you, rules, &step));
+
+        match next {
+            Some(next) => step = step.walk(next, board, rules),
+            None => return false,
+        }
+    }
+
+    step.state.health > 0
+}
+
+// First move of the shortest way from the head to the tail of the body,
+// going around it and every other snake. None while the tail is stacked.
+fn get_tail_move(
+    board: &Board,
+    you: &Battlesnake,
+    rules: &Rules,
+    body: &[Coord],
+) -> Option<Direction> {
+    // Just after eating the tail is stacked, and stays put for a turn.
+    let tail_moves = !rules.solid_body(body).contains(body.last()?);
+
+    find_tail(board, you, rules, body, tail_moves)
+}
+
+// Any safe step after which the tail is still in reach, to wait for a
+// stacked tail to move again.
+fn get_waiting_move(
+    board: &Board,
+    you: &Battlesnake,
+    rules: &Rules,
+    step: &Step,
+) -> Option<Direction> {
+    let blocked = get_blocked(board, you, rules, &step.state.body);
+
+    rules
+        .topology
+        .neighbours(board, &step.state.head)
+        .into_iter()
+        .filter(|dir| {
+            let coord = dir.get_coord();
+
+            rules.topology.contains(board, coord) && !blocked[coord.y as usize][coord.x as usize]
+        })
+        .find(|dir| {
+            let next = step.walk(*dir, board, rules);
+
+            next.state.health > 0 && find_tail(board, you, rules, &next.state.body, true).is_some()
+        })
+}
+
+// Cells taken by the body and every other snake.
+fn get_blocked(board: &Board, you: &Battlesnake, rules: &Rules, body: &[Coord]) -> Vec<Vec<bool>> {
+    let mut blocked = vec![vec![false; board.width as usize]; board.height as usize];
+
+    for segment in board
+        .snakes
+        .iter()
+        .filter(|snake| snake.id != you.id)
+        .flat_map(|snake| rules.solid_body(&snake.body))
+        .chain(rules.solid_body(body))
+        .filter(|segment| rules.topology.contains(board, segment))
+    {
+        blocked[segment.y as usize][segment.x as usize] = true;
+    }
+
+    blocked
+}
+
+// Breadth first search from the head to the tail, which is never found if
+// it doesn't move.
+fn find_tail(
+    board: &Board,
+    you: &Battlesnake,
+    rules: &Rules,
+    body: &[Coord],
+    tail_moves: bool,
+) -> Option<Direction> {
+    let (head, tail) = (body.first()?, body.last()?);
+    let mut blocked = get_blocked(board, you, rules, body);
+
+    let mut queue: VecDeque<(Coord, Direction)> = VecDeque::new();
+
+    for dir in rules.topology.neighbours(board, head) {
+        queue.push_back((*dir.get_coord(), dir));
+    }
+
+    while let Some((coord, first)) = queue.pop_front() {
+        if !rules.topology.contains(board, &coord) {
+            continue;
+        }
+
+        if coord == *tail && tail_moves {
+            return Some(first);
+        }
+
+        let (x, y) = (coord.x as usize, coord.y as usize);
+
+        if blocked[y][x] {
+            continue;
+        }
+
+        blocked[y][x] = true;
+
+        for dir in rules.topology.neighbours(board, &coord) {
+            queue.push_back((*dir.get_coord(), first));
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        domain::Ruleset,
+        logic::fixtures::{get_board, get_snake},
+    };
+
+    // A pocket on the bottom left, closed by the enemy and our own neck.
+    fn get_pocket() -> (Board, Battlesnake) {
+        let you = get_snake(
+            "you",
+            &[Coord::new(2, 0), Coord::new(3, 0), Coord::new(4, 0)],
+        );
+        let enemy = get_snake(
+            "enemy",
+            &[
+                Coord::new(0, 2),
+                Coord::new(0, 1),
+                Coord::new(1, 1),
+                Coord::new(1, 2),
+            ],
+        );
+
+        let board = Board {
+            height: 3,
+            width: 5,
+            food: vec![],
+            snakes: vec![you.clone(), enemy],
+            hazards: vec![],
+        };
+
+        (board, you)
+    }
+
+    #[test]
+    fn keep_the_tail_in_reach() {
+        let (board, you) = get_pocket();
+        let options = vec![
+            Direction::Left(Coord::new(1, 0)),
+            Direction::Up(Coord::new(2, 1)),
+        ];
+
+        assert_eq!(
+            chase_tail(&board, &you, &Rules::default(), &options),
+            Some(Direction::Up(Coord::new(2, 1)))
+        );
+        assert_eq!(
+            chase_tail(&board, &you, &Rules::default(), &options[..1]),
+            None
+        );
+    }
+
+    #[test]
+    fn no_chasing_fixed_tails() {
+        let (board, you) = get_pocket();
+        let rules = Rules::new(&Ruleset::new("constrictor"));
+
+        assert_eq!(
+            chase_tail(&board, &you, &rules, &[Direction::Up(Coord::new(2, 1))]),
+            None
+        );
+    }
+
+    #[test]
+    fn wait_for_stacked_tails() {
+        let mut body = vec![
+            Coord::new(0, 0),
+            Coord::new(1, 0),
+            Coord::new(1, 1),
+            Coord::new(0, 1),
+        ];
+        let you = get_snake("you", &body);
+        let board = get_board(3, vec![], &[]);
+        let rules = Rules::default();
+
+        assert_eq!(
+            get_tail_move(&board, &you, &rules, &body),
+            Some(Direction::Up(Coord::new(0, 1)))
+        );
+
+        // We just ate, the tail won't move this turn.
+        body.push(Coord::new(0, 1));
+        assert_eq!(get_tail_move(&board, &you, &rules, &body), None);
+    }
+
+    #[test]
+    fn stall_over_food() {
+        let you = get_snake(
+            "you",
+            &[Coord::new(2, 2), Coord::new(3, 2), Coord::new(4, 2)],
+        );
+        let board = get_board(7, vec![you.clone()], &[Coord::new(2, 1)]);
+        let rules = Rules::default();
+
+        // Eating stacks the tail, we step aside until it moves again.
+        assert!(can_stall(
+            &board,
+            &you,
+            &rules,
+            Direction::Down(Coord::new(2, 1))
+        ));
+        assert!(can_stall(
+            &board,
+            &you,
+            &rules,
+            Direction::Up(Coord::new(2, 3))
+        ));
+    }
+}
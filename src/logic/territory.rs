@@ -0,0 +1,213 @@
+use std::collections::HashMap;
+
+use crate::domain::{Battlesnake, Board, Coord, Direction};
+
+use super::{rules::Rules, space::get_free_turns};
+
+// Food is worth this many plain cells when scoring a territory.
+pub const FOOD_WEIGHT: usize = 3;
+
+// Moves scoring below this share of the best one are dropped.
+const MIN_SCORE_RATIO: f64 = 0.9;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Territory {
+    pub cells: usize,
+    pub food: usize,
+}
+
+impl Territory {
+    pub fn score(&self) -> usize {
+        self.cells + FOOD_WEIGHT * self.food
+    }
+}
+
+// Voronoi partition of the board: every cell belongs to the snake whose head
+// reaches it first. Ties go to the longer snake, or to nobody if they have
+// the same length.
+pub fn get_territories(board: &Board, rules: &Rules) -> HashMap<String, Territory> {
+    let free_at = get_free_turns(board, rules);
+    let (width, height) = (board.width as usize, board.height as usize);
+
+    let mut claimed = vec![vec![false; width]; height];
+    let mut territories: HashMap<String, Territory> = board
+        .snakes
+        .iter()
+        .map(|snake| (snake.id.clone(), Territory::default()))
+        .collect();
+
+    let mut frontier: Vec<(Coord, usize)> = vec![];
+
+    for (i, snake) in board.snakes.iter().enumerate() {
+        if rules.topology.contains(board, &snake.head) {
+            claimed[snake.head.y as usize][snake.head.x as usize] = true;
+            frontier.push((snake.head, i));
+        }
+    }
+
+    let mut turn = 0;
+
+    while !frontier.is_empty() {
+        turn += 1;
+
+        // Best claim on every cell reached this turn: the owner and its length.
+        let mut claims: HashMap<Coord, (Option<usize>, usize)> = HashMap::new();
+
+        for (coord, owner) in frontier.iter() {
+            let length = board.snakes[*owner].body.len();
+
+            for dir in rules.topology.neighbours(board, coord) {
+                let next = *dir.get_coord();
+
+                if !rules.topology.contains(board, &next) {
+                    continue;
+                }
+
+                let (x, y) = (next.x as usize, next.y as usize);
+
+                if claimed[y][x] || free_at[y][x] > turn {
+                    continue;
+                }
+
+                let claim = claims.entry(next).or_insert((Some(*owner), length));
+
+                if length > claim.1 {
+                    *claim = (Some(*owner), length);
+                } else if length == claim.1 && claim.0 != Some(*owner) {
+                    claim.0 = None;
+                }
+            }
+        }
+
+        frontier = vec![];
+
+        for (coord, (owner, _)) in claims {
+            claimed[coord.y as usize][coord.x as usize] = true;
+
+            if let Some(owner) = owner {
+                let territory = territories.get_mut(&board.snakes[owner].id).unwrap();
+
+                territory.cells += 1;
+
+                if board.food.contains(&coord) {
+                    territory.food += 1;
+                }
+
+                frontier.push((coord, owner));
+            }
+        }
+    }
+
+    territories
+}
+
+// Keep the moves leaving us with the most territory.
+pub fn prefer_territory(
+    options: &[Direction],
+    board: &Board,
+    you: &Battlesnake,
+    rules: &Rules,
+) -> Vec<Direction> {
+    let scores: Vec<(Direction, usize)> = options
+        .iter()
+        .map(|opt| (*opt, get_score(opt, board, you, rules)))
+        .collect();
+
+    let best = scores.iter().map(|(_, score)| *score).max().unwrap_or(0);
+
+    scores
+        .into_iter()
+        .filter(|(_, score)| *score as f64 >= best as f64 * MIN_SCORE_RATIO)
+        .map(|(opt, _)| opt)
+        .collect()
+}
+
+// Our territory once we take the move and the others stay put.
+fn get_score(option: &Direction, board: &Board, you: &Battlesnake, rules: &Rules) -> usize {
+    let mut next = board.clone();
+
+    for snake in next.snakes.iter_mut().filter(|snake| snake.id == you.id) {
+        snake.body.insert(0, *option.get_coord());
+        snake.head = *option.get_coord();
+
+        if rules.tails_vacate() {
+            snake.body.pop();
+        }
+    }
+
+    get_territories(&next, rules)
+        .get(&you.id)
+        .map(|territory| territory.score())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logic::fixtures::{get_board, get_snake};
+
+    #[test]
+    fn split_board() {
+        let board = get_board(
+            5,
+            vec![
+                get_snake("left", &[Coord::new(0, 2), Coord::new(0, 1)]),
+                get_snake("right", &[Coord::new(4, 2), Coord::new(4, 1)]),
+            ],
+            &[Coord::new(1, 4)],
+        );
+
+        let territories = get_territories(&board, &Rules::default());
+
+        // The middle column is equally far from both heads.
+        assert_eq!(territories["left"], Territory { cells: 9, food: 1 });
+        assert_eq!(territories["right"], Territory { cells: 9, food: 0 });
+    }
+
+    #[test]
+    fn ties_go_to_longer_snake() {
+        let board = get_board(
+            5,
+            vec![
+                get_snake(
+                    "long",
+                    &[Coord::new(0, 2), Coord::new(0, 1), Coord::new(0, 0)],
+                ),
+                get_snake("short", &[Coord::new(4, 2), Coord::new(4, 1)]),
+            ],
+            &[],
+        );
+
+        let territories = get_territories(&board, &Rules::default());
+
+        // The middle column goes to the longer snake.
+        assert_eq!(territories["long"].cells, 14);
+        assert_eq!(territories["short"].cells, 9);
+    }
+
+    #[test]
+    fn prefer_open_space() {
+        let you = get_snake("you", &[Coord::new(1, 2), Coord::new(0, 2)]);
+        let board = get_board(
+            5,
+            vec![
+                you.clone(),
+                get_snake(
+                    "enemy",
+                    &[Coord::new(3, 2), Coord::new(4, 2), Coord::new(4, 1)],
+                ),
+            ],
+            &[],
+        );
+
+        let options = vec![
+            Direction::Up(Coord::new(1, 3)),
+            Direction::Right(Coord::new(2, 2)),
+            Direction::Down(Coord::new(1, 1)),
+        ];
+
+        let response = prefer_territory(&options, &board, &you, &Rules::default());
+
+        assert_eq!(response, vec![Direction::Right(Coord::new(2, 2))]);
+    }
+}
@@ -0,0 +1,128 @@
+use crate::domain::{Board, Coord, Direction, GameMode};
+
+// Anything laid out as a grid of cells.
+pub trait Dimensions {
+    fn width(&self) -> u32;
+    fn height(&self) -> u32;
+}
+
+impl Dimensions for Board {
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+}
+
+// How the cells of the board connect to each other. In wrapped games moving
+// off one edge puts you on the opposite one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Topology {
+    Bounded,
+    Wrapped,
+}
+
+impl Topology {
+    pub fn new(mode: GameMode) -> Self {
+        match mode {
+            GameMode::Wrapped => Topology::Wrapped,
+            _ => Topology::Bounded,
+        }
+    }
+
+    // All the available moves from the coord, always in the same order:
+    // up, right, down, left. Bounded boards may return coords outside them.
+    pub fn neighbours(&self, board: &impl Dimensions, coord: &Coord) -> [Direction; 4] {
+        let Coord { x, y } = *coord;
+
+        [
+            Direction::Up(self.normalize(board, Coord::new(x, y + 1))),
+            Direction::Right(self.normalize(board, Coord::new(x + 1, y))),
+            Direction::Down(self.normalize(board, Coord::new(x, y - 1))),
+            Direction::Left(self.normalize(board, Coord::new(x - 1, y))),
+        ]
+    }
+
+    pub fn contains(&self, board: &impl Dimensions, coord: &Coord) -> bool {
+        coord.x >= 0
+            && (coord.x as u32) < board.width()
+            && coord.y >= 0
+            && (coord.y as u32) < board.height()
+    }
+
+    pub fn normalize(&self, board: &impl Dimensions, coord: Coord) -> Coord {
+        match self {
+            Topology::Bounded => coord,
+            Topology::Wrapped => Coord::new(
+                coord.x.rem_euclid(board.width() as i32),
+                coord.y.rem_euclid(board.height() as i32),
+            ),
+        }
+    }
+
+    // The move that takes `from` into the adjacent `to`, if any.
+    pub fn direction(
+        &self,
+        board: &impl Dimensions,
+        from: &Coord,
+        to: &Coord,
+    ) -> Option<Direction> {
+        self.neighbours(board, from)
+            .into_iter()
+            .find(|dir| dir.get_coord() == to)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logic::fixtures::get_board;
+
+    #[test]
+    fn bounded_neighbours() {
+        let neighbours =
+            Topology::Bounded.neighbours(&get_board(5, vec![], &[]), &Coord::new(0, 4));
+
+        assert_eq!(
+            neighbours,
+            [
+                Direction::Up(Coord::new(0, 5)),
+                Direction::Right(Coord::new(1, 4)),
+                Direction::Down(Coord::new(0, 3)),
+                Direction::Left(Coord::new(-1, 4)),
+            ]
+        );
+    }
+
+    #[test]
+    fn wrapped_neighbours() {
+        let neighbours =
+            Topology::Wrapped.neighbours(&get_board(5, vec![], &[]), &Coord::new(0, 4));
+
+        assert_eq!(
+            neighbours,
+            [
+                Direction::Up(Coord::new(0, 0)),
+                Direction::Right(Coord::new(1, 4)),
+                Direction::Down(Coord::new(0, 3)),
+                Direction::Left(Coord::new(4, 4)),
+            ]
+        );
+    }
+
+    #[test]
+    fn wrapped_direction() {
+        let board = get_board(5, vec![], &[]);
+
+        assert_eq!(
+            Topology::Wrapped.direction(&board, &Coord::new(4, 2), &Coord::new(0, 2)),
+            Some(Direction::Right(Coord::new(0, 2)))
+        );
+        assert_eq!(
+            Topology::Bounded.direction(&board, &Coord::new(4, 2), &Coord::new(0, 2)),
+            None
+        );
+    }
+}
@@ -0,0 +1,95 @@
+use crate::domain::Direction;
+
+// Entries kept per game, a power of two so the key can be masked.
+const TABLE_SIZE: usize = 1 << 16;
+
+// How the stored value relates to the real one, after alpha-beta cut-offs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    // The real value is at least this one.
+    Lower,
+    // The real value is at most this one.
+    Upper,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Entry {
+    pub key: u64,
+    pub depth: u32,
+    pub bound: Bound,
+    pub value: i32,
+    pub best: Option<Direction>,
+}
+
+// Fixed size table of searched positions, indexed by Zobrist key. Newer and
+// deeper searches replace older ones.
+#[derive(Debug)]
+pub struct TranspositionTable {
+    entries: Vec<Option<Entry>>,
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        TranspositionTable::new(TABLE_SIZE)
+    }
+}
+
+impl TranspositionTable {
+    // `size` is rounded up to a power of two.
+    pub fn new(size: usize) -> Self {
+        TranspositionTable {
+            entries: vec![None; size.max(1).next_power_of_two()],
+        }
+    }
+
+    fn slot(&self, key: u64) -> usize {
+        key as usize & (self.entries.len() - 1)
+    }
+
+    pub fn get(&self, key: u64) -> Option<&Entry> {
+        self.entries[self.slot(key)]
+            .as_ref()
+            .filter(|entry| entry.key == key)
+    }
+
+    // Keeps the deeper entry when both are about the same position.
+    pub fn insert(&mut self, entry: Entry) {
+        let slot = self.slot(entry.key);
+
+        match &self.entries[slot] {
+            Some(old) if old.key == entry.key && old.depth > entry.depth => {}
+            _ => self.entries[slot] = Some(entry),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Coord;
+
+    fn get_entry(key: u64, depth: u32) -> Entry {
+        Entry {
+            key,
+            depth,
+            bound: Bound::Exact,
+            value: depth as i32,
+            best: Some(Direction::Up(Coord::new(0, 1))),
+        }
+    }
+
+    #[test]
+    fn keep_deeper_entries() {
+        let mut table = TranspositionTable::new(4);
+
+        table.insert(get_entry(1, 3));
+        table.insert(get_entry(1, 2));
+        assert_eq!(table.get(1).map(|entry| entry.depth), Some(3));
+
+        // Same slot, different position.
+        table.insert(get_entry(5, 1));
+        assert_eq!(table.get(1), None);
+        assert_eq!(table.get(5).map(|entry| entry.depth), Some(1));
+    }
+}
@@ -0,0 +1,185 @@
+use std::sync::OnceLock;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use super::{
+    bitboard::{BitBoard, MAX_CELLS},
+    simulate::MAX_HEALTH,
+};
+
+// Snakes past this index share keys with the first ones. It only makes
+// collisions a bit more likely.
+const MAX_SNAKES: usize = 8;
+
+// Hazards stacked higher than this share the key of the highest stack.
+const MAX_STACKS: usize = 15;
+
+// Where each segment lies from the one before it: stacked, up, right, down
+// or left.
+const LINKS: usize = 5;
+
+// Fixed so hashes stay the same between turns and restarts.
+const SEED: u64 = 0x5eed_5a4e;
+
+struct Keys {
+    heads: Vec<u64>,
+    bodies: Vec<u64>,
+    lengths: Vec<u64>,
+    health: Vec<u64>,
+    food: Vec<u64>,
+    hazards: Vec<u64>,
+}
+
+fn keys() -> &'static Keys {
+    static KEYS: OnceLock<Keys> = OnceLock::new();
+
+    KEYS.get_or_init(|| {
+        let mut rng = StdRng::seed_from_u64(SEED);
+        let mut random = |len: usize| (0..len).map(|_| rng.gen()).collect::<Vec<u64>>();
+
+        Keys {
+            heads: random(MAX_SNAKES * MAX_CELLS),
+            bodies: random(MAX_SNAKES * MAX_CELLS * LINKS),
+            lengths: random(MAX_SNAKES * (MAX_CELLS + 1)),
+            health: random(MAX_SNAKES * (MAX_HEALTH as usize + 1)),
+            food: random(MAX_CELLS),
+            hazards: random(MAX_CELLS * (MAX_STACKS + 1)),
+        }
+    })
+}
+
+// Zobrist key of the position: living snakes (head, the way each segment
+// follows the previous one, length and health), food and hazard stacks.
+pub fn hash(board: &BitBoard) -> u64 {
+    let keys = keys();
+    let mut hash = 0;
+
+    for i in board.alive() {
+        let snake = &board.snakes[i];
+        let slot = i % MAX_SNAKES;
+        let health = (snake.health.max(0) as usize).min(MAX_HEALTH as usize);
+
+        hash ^= keys.heads[slot * MAX_CELLS + snake.head()];
+        hash ^= keys.lengths[slot * (MAX_CELLS + 1) + snake.len().min(MAX_CELLS)];
+        hash ^= keys.health[slot * (MAX_HEALTH as usize + 1) + health];
+
+        for (i, pair) in snake.body.iter().zip(snake.body.iter().skip(1)).enumerate() {
+            let link = get_link(board, *pair.0 as usize, *pair.1 as usize);
+
+            hash ^= keys.bodies[(slot * MAX_CELLS + i.min(MAX_CELLS - 1)) * LINKS + link];
+        }
+    }
+
+    for cell in board.food.iter() {
+        hash ^= keys.food[cell];
+    }
+
+    for cell in board.hazards.iter() {
+        let stack = (board.hazard_stack(cell) as usize).min(MAX_STACKS);
+
+        hash ^= keys.hazards[cell * (MAX_STACKS + 1) + stack];
+    }
+
+    hash
+}
+
+// Direction from a segment to the next one. Segments a whole row or column
+// apart are neighbours across the edge of a wrapped board.
+fn get_link(board: &BitBoard, from: usize, to: usize) -> usize {
+    let width = board.width as usize;
+    let dx = (to % width) as i64 - (from % width) as i64;
+    let dy = (to / width) as i64 - (from / width) as i64;
+
+    match (dx, dy) {
+        (0, 0) => 0,
+        (0, 1) => 1,
+        (1, 0) => 2,
+        (0, -1) => 3,
+        (-1, 0) => 4,
+        (0, dy) if dy > 0 => 3,
+        (0, _) => 1,
+        (dx, _) if dx > 0 => 4,
+        _ => 2,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        domain::{Board, Coord, Direction},
+        logic::{
+            fixtures::{get_board, get_snake},
+            rules::Rules,
+        },
+    };
+
+    fn get_position(food: Vec<Coord>) -> BitBoard {
+        let snakes = vec![
+            get_snake("you", &[Coord::new(1, 1), Coord::new(1, 0)]),
+            get_snake("enemy", &[Coord::new(5, 5), Coord::new(5, 6)]),
+        ];
+
+        BitBoard::new(&get_board(7, snakes, &food)).unwrap()
+    }
+
+    fn get_coiled_board(body: &[Coord], hazards: Vec<Coord>) -> BitBoard {
+        BitBoard::new(&Board {
+            hazards,
+            ..get_board(7, vec![get_snake("you", body)], &[])
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn same_position_same_hash() {
+        let board = get_position(vec![Coord::new(3, 3)]);
+
+        assert_eq!(hash(&board), hash(&board.clone()));
+        assert_eq!(hash(&board), hash(&get_position(vec![Coord::new(3, 3)])));
+        assert_ne!(hash(&board), hash(&get_position(vec![Coord::new(3, 4)])));
+    }
+
+    #[test]
+    fn hash_segment_order_and_hazard_stacks() {
+        // Same head and cells, the neck and the tail swapped.
+        let neck_left = [
+            Coord::new(1, 1),
+            Coord::new(0, 1),
+            Coord::new(0, 0),
+            Coord::new(1, 0),
+        ];
+        let neck_down = [
+            Coord::new(1, 1),
+            Coord::new(1, 0),
+            Coord::new(0, 0),
+            Coord::new(0, 1),
+        ];
+
+        assert_ne!(
+            hash(&get_coiled_board(&neck_left, vec![])),
+            hash(&get_coiled_board(&neck_down, vec![]))
+        );
+
+        let single = get_coiled_board(&neck_left, vec![Coord::new(3, 3)]);
+        let double = get_coiled_board(&neck_left, vec![Coord::new(3, 3), Coord::new(3, 3)]);
+
+        assert_ne!(hash(&single), hash(&double));
+    }
+
+    #[test]
+    fn hash_changes_every_turn() {
+        let mut board = get_position(vec![]);
+        let before = hash(&board);
+
+        board.advance(
+            &[
+                Some(Direction::Up(Coord::new(1, 2))),
+                Some(Direction::Down(Coord::new(5, 4))),
+            ],
+            &Rules::default(),
+        );
+
+        assert_ne!(hash(&board), before);
+    }
+}
@@ -1,11 +1,8 @@
-#[macro_use]
-extern crate rocket;
-
 use log::info;
 use rocket::fairing::AdHoc;
 use std::env;
 
-use battle_snake_rust::handlers::{handle_end, handle_index, handle_move, handle_start};
+use battle_snake_rust::snakes;
 
 // API and Response Objects
 // See https://docs.battlesnake.com/api
@@ -29,16 +26,14 @@ async fn main() -> shuttle_rocket::ShuttleRocket {
 
     info!("Starting Battlesnake Server...");
 
-    let rocket = rocket::build()
-        .attach(AdHoc::on_response("Server ID Middleware", |_, res| {
-            Box::pin(async move {
-                res.set_raw_header("Server", "battlesnake/github/starter-snake-rust");
-            })
-        }))
-        .mount(
-            "/",
-            routes![handle_index, handle_start, handle_move, handle_end],
-        );
+    let rocket = rocket::build().attach(AdHoc::on_response("Server ID Middleware", |_, res| {
+        Box::pin(async move {
+            res.set_raw_header("Server", "battlesnake/github/starter-snake-rust");
+        })
+    }));
+
+    // Every snake configured in Rocket.toml gets its own routes.
+    let rocket = snakes::mount(rocket);
 
     Ok(rocket.into())
 }
@@ -0,0 +1,361 @@
+use std::{
+    env,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Sender},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    domain::{GameState, MoveResponse},
+    logic::{
+        self,
+        strategy::{get_budgeted_strategy, Budget},
+    },
+    session::Session,
+};
+
+// One line of a game record.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Record {
+    Move {
+        request: GameState,
+        // The strategy that played, missing from older records.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        strategy: Option<String>,
+        response: Value,
+        latency_ms: u64,
+    },
+    End {
+        request: GameState,
+    },
+}
+
+impl Record {
+    pub fn request(&self) -> &GameState {
+        match self {
+            Record::Move { request, .. } | Record::End { request } => request,
+        }
+    }
+}
+
+// Writes every game to `<dir>/<game id>-<hash>.jsonl`. The directory comes
+// from the `BATTLESNAKE_RECORD_DIR` environment variable, nothing is recorded
+// without it. Writing happens on a thread of its own so the disk never slows
+// down a move, and failures are logged, they never get in the way of a game.
+#[derive(Debug, Default)]
+pub struct Recorder {
+    dir: Option<PathBuf>,
+    writer: Option<Writer>,
+}
+
+// The thread the records go to.
+#[derive(Debug)]
+struct Writer {
+    sender: Sender<(PathBuf, Record)>,
+    handle: JoinHandle<()>,
+}
+
+impl Recorder {
+    pub fn new(dir: Option<PathBuf>) -> Self {
+        let writer = dir.clone().map(|dir| {
+            let (sender, receiver) = mpsc::channel::<(PathBuf, Record)>();
+            let handle = thread::spawn(move || {
+                for (path, record) in receiver {
+                    append(&dir, &path, &record);
+                }
+            });
+
+            Writer { sender, handle }
+        });
+
+        Recorder { dir, writer }
+    }
+
+    pub fn from_env() -> Self {
+        Recorder::new(env::var_os("BATTLESNAKE_RECORD_DIR").map(PathBuf::from))
+    }
+
+    // Records of each snake go to a directory of their own, when the server
+    // plays more than one.
+    pub fn for_snake(&self, name: &str) -> Self {
+        match &self.dir {
+            Some(dir) if !name.is_empty() => Recorder::new(Some(dir.join(name))),
+            _ => Recorder::new(self.dir.clone()),
+        }
+    }
+
+    pub fn record_move(
+        &self,
+        request: &GameState,
+        strategy: &str,
+        response: &MoveResponse,
+        latency: Duration,
+    ) {
+        self.write(
+            request,
+            Record::Move {
+                request: request.clone(),
+                strategy: Some(String::from(strategy)),
+                response: serde_json::to_value(response).unwrap_or_default(),
+                latency_ms: latency.as_millis() as u64,
+            },
+        );
+    }
+
+    pub fn record_end(&self, request: &GameState) {
+        self.write(
+            request,
+            Record::End {
+                request: request.clone(),
+            },
+        );
+    }
+
+    fn write(&self, request: &GameState, record: Record) {
+        let (dir, writer) = match (&self.dir, &self.writer) {
+            (Some(dir), Some(writer)) => (dir, writer),
+            _ => return,
+        };
+
+        let path = record_path(dir, request.game.id());
+
+        if writer.sender.send((path, record)).is_err() {
+            warn!("Could not record to {}: the writer is gone", dir.display());
+        }
+    }
+}
+
+// Waits for the pending records to be written.
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        if let Some(writer) = self.writer.take() {
+            drop(writer.sender);
+
+            if writer.handle.join().is_err() {
+                warn!("The record writer panicked");
+            }
+        }
+    }
+}
+
+fn append(dir: &Path, path: &Path, record: &Record) {
+    let result = fs::create_dir_all(dir).and_then(|_| {
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        let line = serde_json::to_string(record)?;
+
+        writeln!(file, "{}", line)
+    });
+
+    if let Err(err) = result {
+        warn!("Could not record to {}: {}", path.display(), err);
+    }
+}
+
+// Game ids come from the outside, keep them from escaping the directory. The
+// hash of the whole id keeps ids that only differ in the replaced characters
+// apart.
+pub fn record_path(dir: &Path, game_id: &str) -> PathBuf {
+    let name: String = game_id
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' => c,
+            _ => '_',
+        })
+        .collect();
+
+    dir.join(format!("{}-{:016x}.jsonl", name, get_hash(game_id)))
+}
+
+// FNV-1a, which unlike the std hasher stays the same from one build to the
+// next, so a restarted server keeps appending to the same files.
+fn get_hash(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+pub fn load(path: &Path) -> io::Result<Vec<Record>> {
+    let reader = BufReader::new(File::open(path)?);
+
+    reader
+        .lines()
+        .filter(|line| !line.as_ref().is_ok_and(|line| line.trim().is_empty()))
+        .map(|line| Ok(serde_json::from_str(&line?)?))
+        .collect()
+}
+
+// A turn where we no longer pick the recorded move.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    pub turn: i32,
+    pub recorded: String,
+    pub replayed: String,
+}
+
+// Asks the strategy that played again for every recorded move, in order and
+// with a session of its own, and reports the turns that come out differently.
+// Records that don't name their strategy go to `fallback`. Strategies are
+// held to the budget instead of the clock, so replays come out the same every
+// time.
+pub fn replay(records: &[Record], fallback: &str, budget: Budget) -> Vec<Divergence> {
+    let mut session = Session::new(Instant::now());
+
+    records
+        .iter()
+        .filter_map(|record| match record {
+            Record::Move {
+                request,
+                strategy,
+                response,
+                ..
+            } => Some((request, strategy.as_deref().unwrap_or(fallback), response)),
+            Record::End { .. } => None,
+        })
+        .filter_map(|(request, name, response)| {
+            let Some(strategy) = get_budgeted_strategy(name, budget) else {
+                warn!("Unknown strategy {} on turn {}", name, request.turn);
+                return None;
+            };
+
+            session.last_seen = Instant::now();
+
+            let replayed = logic::get_move(strategy.as_ref(), request, &mut session);
+
+            let recorded = response["move"].as_str().unwrap_or_default().to_string();
+            let replayed = replayed.r#move.as_str().to_string();
+
+            (recorded != replayed).then_some(Divergence {
+                turn: request.turn,
+                recorded,
+                replayed,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Battlesnake, Board, Coord, Direction, Game, Ruleset};
+
+    fn get_budget() -> Budget {
+        Budget {
+            depth: 2,
+            iterations: 50,
+            seed: 0,
+        }
+    }
+
+    fn get_state(turn: i32) -> GameState {
+        let you = Battlesnake {
+            id: String::from("you"),
+            name: String::from("you"),
+            health: 100,
+            length: 3,
+            body: vec![Coord::new(0, 2), Coord::new(0, 1), Coord::new(0, 0)],
+            head: Coord::new(0, 2),
+            latency: String::from("0"),
+            shout: None,
+        };
+
+        GameState {
+            game: Game::new("../record test", Ruleset::new("standard"), 500),
+            turn,
+            board: Board {
+                height: 3,
+                width: 1,
+                food: vec![],
+                snakes: vec![you.clone()],
+                hazards: vec![],
+            },
+            you,
+        }
+    }
+
+    #[test]
+    fn keep_records_inside_dir() {
+        let path = record_path(Path::new("records"), "../record test");
+
+        assert_eq!(path.parent(), Some(Path::new("records")));
+        assert!(path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with("___record_test-") && name.ends_with(".jsonl")));
+    }
+
+    #[test]
+    fn keep_similar_ids_apart() {
+        let dir = Path::new("records");
+
+        assert_ne!(record_path(dir, "a/b"), record_path(dir, "a_b"));
+        assert_eq!(record_path(dir, "a/b"), record_path(dir, "a/b"));
+    }
+
+    #[test]
+    fn record_and_replay() {
+        let dir = env::temp_dir().join(format!("battlesnake-records-{}", std::process::id()));
+        let recorder = Recorder::new(Some(dir.clone()));
+
+        // Boxed in, the only answer is "up".
+        recorder.record_move(
+            &get_state(1),
+            "mcts",
+            &MoveResponse::new(Direction::Down(Coord::new(0, 1))),
+            Duration::ZERO,
+        );
+        recorder.record_move(
+            &get_state(2),
+            "mcts",
+            &MoveResponse::new(Direction::Up(Coord::new(0, 3))),
+            Duration::ZERO,
+        );
+        recorder.record_end(&get_state(3));
+        drop(recorder);
+
+        let records = load(&record_path(&dir, "../record test")).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(records.len(), 3);
+        assert!(matches!(
+            &records[0],
+            Record::Move { strategy: Some(name), .. } if name == "mcts"
+        ));
+        assert!(matches!(records[2], Record::End { .. }));
+
+        let divergences = replay(&records, "heuristic", get_budget());
+
+        assert_eq!(
+            divergences,
+            vec![Divergence {
+                turn: 1,
+                recorded: String::from("down"),
+                replayed: String::from("up"),
+            }]
+        );
+        assert_eq!(replay(&records, "heuristic", get_budget()), divergences);
+    }
+
+    #[test]
+    fn replay_older_records() {
+        // Written before records named their strategy.
+        let line = serde_json::json!({
+            "type": "move",
+            "request": get_state(1),
+            "response": { "move": "down" },
+            "latency_ms": 0,
+        });
+        let records: Vec<Record> = vec![serde_json::from_value(line).unwrap()];
+
+        assert!(matches!(records[0], Record::Move { strategy: None, .. }));
+        assert_eq!(replay(&records, "heuristic", get_budget()).len(), 1);
+        assert!(replay(&records, "unknown", get_budget()).is_empty());
+    }
+}
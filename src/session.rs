@@ -0,0 +1,330 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    domain::{Board, Coord, Direction},
+    logic::{hunger::Hunger, rules::Rules, transposition::TranspositionTable},
+};
+
+// Games that haven't sent anything for this long are dropped, in case their
+// `/end` request never arrives.
+const DEFAULT_TTL: Duration = Duration::from_secs(10 * 60);
+
+// Least time between two sweeps triggered by `/move` requests, which come
+// in far more often than games get forgotten.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+// Everything we remember about one game between requests.
+#[derive(Debug)]
+pub struct Session {
+    pub started: Instant,
+    // When the last request of the game arrived, moves are due counting
+    // from there.
+    pub last_seen: Instant,
+    pub turn: i32,
+    // Moves every snake made so far, by snake id, oldest first.
+    pub moves: HashMap<String, Vec<Direction>>,
+    // How long each of our moves took to answer.
+    pub timings: Vec<Duration>,
+    // What the strategy is up to this turn, shouted by default.
+    pub plan: Option<String>,
+    heads: HashMap<String, Coord>,
+    pub(crate) table: TranspositionTable,
+    // The hunger the table was searched with, its values only hold for the
+    // evaluation of that hunger.
+    table_hunger: Option<Hunger>,
+}
+
+impl Session {
+    pub fn new(now: Instant) -> Self {
+        Session {
+            started: now,
+            last_seen: now,
+            turn: 0,
+            moves: HashMap::new(),
+            timings: vec![],
+            plan: None,
+            heads: HashMap::new(),
+            table: TranspositionTable::default(),
+            table_hunger: None,
+        }
+    }
+
+    // Works out the move of every snake since the last board we saw.
+    pub fn observe(&mut self, turn: i32, board: &Board, rules: &Rules) {
+        for snake in board.snakes.iter() {
+            let direction = self
+                .heads
+                .get(&snake.id)
+                .and_then(|head| rules.topology.direction(board, head, &snake.head));
+
+            if let Some(direction) = direction {
+                self.moves
+                    .entry(snake.id.clone())
+                    .or_default()
+                    .push(direction);
+            }
+        }
+
+        self.heads = board
+            .snakes
+            .iter()
+            .map(|snake| (snake.id.clone(), snake.head))
+            .collect();
+        self.turn = turn;
+    }
+
+    // The table of the last search, or an empty one if that search
+    // evaluated positions for another hunger.
+    pub(crate) fn take_table(&mut self, hunger: Hunger) -> TranspositionTable {
+        match self.table_hunger.replace(hunger) {
+            Some(last) if last == hunger => std::mem::take(&mut self.table),
+            _ => TranspositionTable::default(),
+        }
+    }
+
+    pub fn average_timing(&self) -> Option<Duration> {
+        let total: Duration = self.timings.iter().sum();
+
+        (!self.timings.is_empty()).then(|| total / self.timings.len() as u32)
+    }
+}
+
+// Sessions of every game being played, meant to live in Rocket managed state.
+// Each session has its own lock, so games don't wait for each other.
+#[derive(Debug)]
+pub struct SessionStore {
+    ttl: Duration,
+    sessions: RwLock<HashMap<String, Arc<Mutex<Session>>>>,
+    last_sweep: Mutex<Option<Instant>>,
+}
+
+impl Default for SessionStore {
+    fn default() -> Self {
+        SessionStore::new(DEFAULT_TTL)
+    }
+}
+
+impl SessionStore {
+    pub fn new(ttl: Duration) -> Self {
+        SessionStore {
+            ttl,
+            sessions: RwLock::new(HashMap::new()),
+            last_sweep: Mutex::new(None),
+        }
+    }
+
+    // Starts the game from scratch, even if we already knew about it.
+    pub fn start(&self, game_id: &str, now: Instant) {
+        let session = Arc::new(Mutex::new(Session::new(now)));
+
+        self.sessions
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(String::from(game_id), session);
+    }
+
+    // Runs `f` on the session of the game, creating it if we missed its
+    // `/start`.
+    pub fn with<T>(&self, game_id: &str, now: Instant, f: impl FnOnce(&mut Session) -> T) -> T {
+        let known = self
+            .sessions
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(game_id)
+            .cloned();
+
+        let session = known.unwrap_or_else(|| {
+            self.sessions
+                .write()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .entry(String::from(game_id))
+                .or_insert_with(|| Arc::new(Mutex::new(Session::new(now))))
+                .clone()
+        });
+
+        let mut session = session
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        session.last_seen = now;
+        f(&mut session)
+    }
+
+    // Forgets the game, running `f` on its session one last time.
+    pub fn end<T>(&self, game_id: &str, f: impl FnOnce(&mut Session) -> T) -> Option<T> {
+        let session = self
+            .sessions
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .remove(game_id)?;
+
+        let mut session = session
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        Some(f(&mut session))
+    }
+
+    // Drops the games not seen for longer than the TTL, returns how many.
+    pub fn sweep(&self, now: Instant) -> usize {
+        let mut sessions = self
+            .sessions
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let before = sessions.len();
+
+        sessions.retain(|_, session| match session.try_lock() {
+            Ok(session) => now.saturating_duration_since(session.last_seen) < self.ttl,
+            // Busy answering a request, so it's alive.
+            Err(_) => true,
+        });
+
+        before - sessions.len()
+    }
+
+    // Sweeps unless it was done less than `SWEEP_INTERVAL` ago, cheap enough
+    // to call on every request.
+    pub fn sweep_due(&self, now: Instant) -> usize {
+        {
+            let mut last_sweep = self
+                .last_sweep
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+            if last_sweep.is_some_and(|last| now.saturating_duration_since(last) < SWEEP_INTERVAL) {
+                return 0;
+            }
+
+            *last_sweep = Some(now);
+        }
+
+        self.sweep(now)
+    }
+
+    pub fn len(&self) -> usize {
+        self.sessions
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        domain::Battlesnake,
+        logic::transposition::{Bound, Entry},
+    };
+
+    fn get_board(head: Coord) -> Board {
+        Board {
+            height: 7,
+            width: 7,
+            food: vec![],
+            snakes: vec![Battlesnake {
+                id: String::from("enemy"),
+                name: String::from("enemy"),
+                health: 100,
+                length: 1,
+                body: vec![head],
+                head,
+                latency: String::from("0"),
+                shout: None,
+            }],
+            hazards: vec![],
+        }
+    }
+
+    #[test]
+    fn forget_table_of_other_hunger() {
+        let mut session = Session::new(Instant::now());
+        let entry = Entry {
+            key: 42,
+            depth: 3,
+            bound: Bound::Exact,
+            value: 10,
+            best: None,
+        };
+
+        session.table = session.take_table(Hunger::Feed);
+        session.table.insert(entry);
+
+        session.table = session.take_table(Hunger::Feed);
+        assert_eq!(session.table.get(42), Some(&entry));
+
+        session.table = session.take_table(Hunger::Idle);
+        assert_eq!(session.table.get(42), None);
+    }
+
+    #[test]
+    fn keep_games_apart() {
+        let store = SessionStore::default();
+        let now = Instant::now();
+
+        store.start("a", now);
+        store.with("a", now, |session| session.turn = 3);
+        store.with("b", now, |session| session.turn = 7);
+
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.with("a", now, |session| session.turn), 3);
+        assert_eq!(store.end("b", |session| session.turn), Some(7));
+        assert_eq!(store.end("b", |session| session.turn), None);
+        assert_eq!(store.len(), 1);
+    }
+
+    #[test]
+    fn sweep_forgotten_games() {
+        let store = SessionStore::new(Duration::from_secs(60));
+        let now = Instant::now();
+
+        store.start("old", now);
+        store.start("new", now + Duration::from_secs(50));
+
+        assert_eq!(store.sweep(now + Duration::from_secs(70)), 1);
+        assert_eq!(store.end("new", |_| ()), Some(()));
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn sweep_once_in_a_while() {
+        let store = SessionStore::new(Duration::from_secs(10));
+        let now = Instant::now();
+
+        store.start("a", now);
+        store.start("b", now);
+
+        assert_eq!(store.sweep_due(now), 0);
+        assert_eq!(store.sweep_due(now + Duration::from_secs(20)), 0);
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.sweep_due(now + SWEEP_INTERVAL), 2);
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn remember_opponent_moves() {
+        let mut session = Session::new(Instant::now());
+        let rules = Rules::default();
+
+        session.observe(0, &get_board(Coord::new(3, 3)), &rules);
+        session.observe(1, &get_board(Coord::new(3, 4)), &rules);
+        session.observe(2, &get_board(Coord::new(2, 4)), &rules);
+
+        assert_eq!(
+            session.moves["enemy"],
+            vec![
+                Direction::Up(Coord::new(3, 4)),
+                Direction::Left(Coord::new(2, 4)),
+            ]
+        );
+        assert_eq!(session.turn, 2);
+    }
+}
@@ -0,0 +1,369 @@
+use std::{collections::HashSet, sync::Arc};
+
+use log::{error, info, warn};
+use rocket::{
+    fairing::AdHoc,
+    http::Status,
+    request::{FromRequest, Outcome, Request},
+    routes, Build, Rocket,
+};
+use serde::Deserialize;
+
+use crate::{
+    domain::InfoResponse,
+    handlers::{handle_end, handle_index, handle_move, handle_start, Arrival},
+    logic::strategy::Strategies,
+    record::Recorder,
+    session::SessionStore,
+};
+
+// One snake served by this server, from the `snakes` list of `Rocket.toml`:
+//
+//     [[default.snakes]]
+//     name = "greedy"
+//     strategy = "heuristic"
+//     color = "#ff8800"
+//
+// It's served under `/<name>/`. Without a strategy it plays the ones of the
+// `BATTLESNAKE_STRATEGY` environment variable. The rest of its info comes from
+// the `info` table, shared by every snake:
+//
+//     [default.info]
+//     author = "me"
+//     head = "smile"
+//
+// Both can be set from the environment too, like
+// `ROCKET_INFO='{color="#ff8800"}'`.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct SnakeConfig {
+    pub name: String,
+    pub strategy: Option<String>,
+    pub author: Option<String>,
+    pub color: Option<String>,
+    pub head: Option<String>,
+    pub tail: Option<String>,
+    pub version: Option<String>,
+}
+
+impl SnakeConfig {
+    fn get_info(&self, info: &InfoResponse) -> InfoResponse {
+        let pick =
+            |value: &Option<String>, default: &String| value.clone().unwrap_or(default.clone());
+
+        InfoResponse {
+            author: pick(&self.author, &info.author),
+            color: pick(&self.color, &info.color),
+            head: pick(&self.head, &info.head),
+            tail: pick(&self.tail, &info.tail),
+            version: self.version.clone().or(info.version.clone()),
+            ..info.clone()
+        }
+    }
+}
+
+pub struct Snake {
+    pub name: String,
+    pub path: String,
+    pub info: InfoResponse,
+    // Shared with the threads the moves are searched on.
+    pub strategies: Arc<Strategies>,
+    pub sessions: Arc<SessionStore>,
+    pub recorder: Recorder,
+}
+
+impl Snake {
+    pub fn new(config: SnakeConfig, info: &InfoResponse) -> Self {
+        let strategies = match &config.strategy {
+            Some(names) => Strategies::new(names),
+            None => Strategies::from_env(),
+        };
+
+        Snake {
+            path: format!("/{}", config.name),
+            recorder: Recorder::from_env().for_snake(&config.name),
+            info: config.get_info(info),
+            name: config.name,
+            strategies: Arc::new(strategies),
+            sessions: Arc::default(),
+        }
+    }
+}
+
+// Every snake of the server, found by the base path of the route handling
+// the request.
+pub struct Snakes(Vec<Snake>);
+
+impl Snakes {
+    pub fn get(&self, path: &str) -> Option<&Snake> {
+        self.0.iter().find(|snake| snake.path == path)
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for &'r Snake {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let snake = req
+            .rocket()
+            .state::<Snakes>()
+            .and_then(|snakes| req.route().and_then(|route| snakes.get(route.uri.base())));
+
+        match snake {
+            Some(snake) => Outcome::Success(snake),
+            None => Outcome::Error((Status::NotFound, ())),
+        }
+    }
+}
+
+// Names end up in paths, keep them simple. An empty one would take `/` from
+// the others.
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+// Mounts the routes of every configured snake, or of a single one at `/`
+// if there are none. Rocket won't start if the info or the snakes don't parse,
+// or any snake has invalid info.
+pub fn mount(rocket: Rocket<Build>) -> Rocket<Build> {
+    let mut errors: Vec<String> = vec![];
+
+    let info: InfoResponse = match rocket.figment().extract_inner("info") {
+        Ok(info) => info,
+        Err(err) if err.missing() => InfoResponse::default(),
+        Err(err) => {
+            errors.push(format!("Invalid info configuration: {}", err));
+            InfoResponse::default()
+        }
+    };
+
+    let mut configs: Vec<SnakeConfig> = match rocket.figment().extract_inner("snakes") {
+        Ok(configs) => configs,
+        Err(err) if err.missing() => vec![],
+        Err(err) => {
+            errors.push(format!("Invalid snakes configuration: {}", err));
+            vec![]
+        }
+    };
+
+    let mut names = HashSet::new();
+
+    configs.retain(|config| {
+        if !is_valid_name(&config.name) {
+            warn!("Skipping snake with invalid name {:?}", config.name);
+            return false;
+        }
+
+        // Two snakes can't share a path, the first one keeps it.
+        if !names.insert(config.name.clone()) {
+            warn!("Skipping snake with duplicate name {:?}", config.name);
+            return false;
+        }
+
+        true
+    });
+
+    if configs.is_empty() {
+        configs.push(SnakeConfig::default());
+    }
+
+    let snakes: Vec<Snake> = configs
+        .into_iter()
+        .map(|config| Snake::new(config, &info))
+        .collect();
+    let mut rocket = rocket;
+
+    for snake in snakes.iter() {
+        info!(
+            "Serving {:?} at {} ({})",
+            snake.name,
+            snake.path,
+            snake.strategies.names().join(", ")
+        );
+
+        rocket = rocket.mount(
+            snake.path.as_str(),
+            routes![handle_index, handle_start, handle_move, handle_end],
+        );
+    }
+
+    rocket
+        .manage(Snakes(snakes))
+        .attach(Arrival::fairing())
+        .attach(AdHoc::try_on_ignite(
+            "Snake info",
+            move |rocket| async move {
+                errors.extend(
+                    rocket
+                        .state::<Snakes>()
+                        .iter()
+                        .flat_map(|snakes| snakes.0.iter())
+                        .filter_map(|snake| {
+                            let err = snake.info.validate().err()?;
+
+                            Some(format!("Snake {:?}: {}", snake.name, err))
+                        }),
+                );
+
+                for err in errors.iter() {
+                    error!("{}", err);
+                }
+
+                match errors.is_empty() {
+                    true => Ok(rocket),
+                    false => Err(rocket),
+                }
+            },
+        ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use rocket::{
+        error::ErrorKind,
+        figment::Figment,
+        local::blocking::Client,
+        serde::json::{serde_json::json, Value},
+        Config,
+    };
+
+    use super::*;
+    use crate::domain::{Battlesnake, Board, Coord, Game, GameState, Ruleset};
+
+    fn get_client() -> Client {
+        let figment = Figment::from(Config::debug_default())
+            .merge((
+                "snakes",
+                json!([
+                    { "name": "greedy", "strategy": "heuristic", "color": "#ff0000" },
+                    { "name": "minimax", "strategy": "minimax" },
+                    { "name": "../bad" },
+                    { "name": "" },
+                    { "name": "greedy", "color": "#0000ff" },
+                ]),
+            ))
+            .merge(("info", json!({ "author": "someone", "color": "#00ff00" })));
+
+        Client::tracked(mount(rocket::custom(figment))).unwrap()
+    }
+
+    #[test]
+    fn serve_every_snake() {
+        let client = get_client();
+
+        let greedy: Value = client.get("/greedy").dispatch().into_json().unwrap();
+        let minimax: Value = client.get("/minimax").dispatch().into_json().unwrap();
+
+        assert_eq!(greedy["color"], "#ff0000");
+        assert_eq!(minimax["color"], "#00ff00");
+        assert_eq!(minimax["author"], "someone");
+        assert_eq!(client.get("/").dispatch().status(), Status::NotFound);
+    }
+
+    #[test]
+    fn skip_empty_and_duplicate_names() {
+        let client = get_client();
+        let snakes = client.rocket().state::<Snakes>().unwrap();
+        let names: Vec<&str> = snakes.0.iter().map(|snake| snake.name.as_str()).collect();
+
+        assert_eq!(names, vec!["greedy", "minimax"]);
+        assert_eq!(snakes.get("/greedy").unwrap().info.color, "#ff0000");
+    }
+
+    #[test]
+    fn answer_moves_off_the_workers() {
+        let client = get_client();
+        let you = Battlesnake {
+            id: String::from("you"),
+            name: String::from("you"),
+            health: 100,
+            length: 3,
+            body: vec![Coord::new(0, 2), Coord::new(0, 1), Coord::new(0, 0)],
+            head: Coord::new(0, 2),
+            latency: String::from("0"),
+            shout: None,
+        };
+        let state = GameState {
+            game: Game::new("game", Ruleset::new("standard"), 500),
+            turn: 1,
+            board: Board {
+                height: 3,
+                width: 1,
+                food: vec![],
+                snakes: vec![you.clone()],
+                hazards: vec![],
+            },
+            you,
+        };
+
+        let response: Value = client
+            .post("/greedy/move")
+            .json(&state)
+            .dispatch()
+            .into_json()
+            .unwrap();
+        let snakes = client.rocket().state::<Snakes>().unwrap();
+        let timings =
+            snakes
+                .get("/greedy")
+                .unwrap()
+                .sessions
+                .with("game", Instant::now(), |session| session.timings.len());
+
+        // Boxed in, the only answer is "up".
+        assert_eq!(response["move"], "up");
+        assert_eq!(timings, 1);
+    }
+
+    #[test]
+    fn refuse_invalid_colors() {
+        let figment = Figment::from(Config::debug_default())
+            .merge(("snakes", json!([{ "name": "greedy", "color": "red" }])));
+
+        let err = Client::tracked(mount(rocket::custom(figment))).err();
+
+        assert!(matches!(
+            err.as_ref().map(|err| err.kind()),
+            Some(ErrorKind::FailedFairings(_))
+        ));
+    }
+
+    #[test]
+    fn refuse_invalid_info() {
+        let figment =
+            Figment::from(Config::debug_default()).merge(("info", json!({ "author": ["a", "b"] })));
+
+        let err = Client::tracked(mount(rocket::custom(figment))).err();
+
+        assert!(matches!(
+            err.as_ref().map(|err| err.kind()),
+            Some(ErrorKind::FailedFairings(_))
+        ));
+    }
+
+    #[test]
+    fn refuse_invalid_snakes() {
+        let figment = Figment::from(Config::debug_default())
+            .merge(("snakes", json!([{ "name": ["greedy"] }])));
+
+        let err = Client::tracked(mount(rocket::custom(figment))).err();
+
+        assert!(matches!(
+            err.as_ref().map(|err| err.kind()),
+            Some(ErrorKind::FailedFairings(_))
+        ));
+    }
+
+    #[test]
+    fn single_snake_by_default() {
+        let client = Client::tracked(mount(rocket::custom(Config::debug_default()))).unwrap();
+
+        assert_eq!(client.get("/").dispatch().status(), Status::Ok);
+    }
+}